@@ -0,0 +1,79 @@
+//! Built-in Application Templates
+
+use anyhow::{anyhow, Context, Result};
+
+/// Template path prefix selecting an embedded template (e.g. `builtin:kitty`)
+pub const PREFIX: &str = "builtin:";
+
+/// Template compiled into the binary
+pub struct Builtin {
+    pub name: &'static str,
+    /// Default render destination when the config omits a target
+    pub target: &'static str,
+    pub template: &'static str,
+}
+
+/// All embedded templates sorted by name
+pub const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "alacritty",
+        target: "~/.config/alacritty/wallbash.toml",
+        template: include_str!("../templates/alacritty.toml.j2"),
+    },
+    Builtin {
+        name: "dunst",
+        target: "~/.config/dunst/dunstrc.d/wallbash.conf",
+        template: include_str!("../templates/dunstrc.j2"),
+    },
+    Builtin {
+        name: "foot",
+        target: "~/.config/foot/wallbash.ini",
+        template: include_str!("../templates/foot.ini.j2"),
+    },
+    Builtin {
+        name: "gtk",
+        target: "~/.config/gtk-4.0/wallbash.css",
+        template: include_str!("../templates/gtk.css.j2"),
+    },
+    Builtin {
+        name: "hyprland",
+        target: "~/.config/hypr/wallbash.conf",
+        template: include_str!("../templates/hyprland.conf.j2"),
+    },
+    Builtin {
+        name: "kitty",
+        target: "~/.config/kitty/wallbash.conf",
+        template: include_str!("../templates/kitty.conf.j2"),
+    },
+    Builtin {
+        name: "rofi",
+        target: "~/.config/rofi/wallbash.rasi",
+        template: include_str!("../templates/rofi.rasi.j2"),
+    },
+    Builtin {
+        name: "waybar",
+        target: "~/.config/waybar/wallbash.css",
+        template: include_str!("../templates/waybar.css.j2"),
+    },
+];
+
+/// Lookup an embedded template by name
+pub fn get(name: &str) -> Result<&'static Builtin> {
+    BUILTINS
+        .iter()
+        .find(|b| b.name == name)
+        .ok_or_else(|| anyhow!("no builtin template named {name:?}"))
+}
+
+/// Lookup an embedded template from a `builtin:` template path
+pub fn from_path(path: &str) -> Option<Result<&'static Builtin>> {
+    path.strip_prefix(PREFIX).map(get)
+}
+
+/// Read template content from the filesystem or the embedded templates
+pub fn read_template(path: &str) -> Result<String> {
+    match from_path(path) {
+        Some(builtin) => Ok(builtin?.template.to_owned()),
+        None => std::fs::read_to_string(path).context("file read failed"),
+    }
+}
@@ -1,21 +1,149 @@
 //! Cli Implementation
 
+use std::collections::{hash_map::Entry, HashMap};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use clap::{Args, Parser, Subcommand};
 
-use crate::color::{Gradiant, Palette};
-use crate::config::Config;
-use crate::image::RawImage;
-use crate::template::Engine;
+use wallbash::builtin::{self, BUILTINS};
+use wallbash::color::{Color, Gradiant, Palette, PaletteSet, Primaries, Theme, ThemeMode};
+use wallbash::config::{Config, Layer, TemplateRender};
+use wallbash::error::Error;
+use wallbash::format::PaletteFormat;
+use wallbash::harmony::{Harmony, Variation};
+use wallbash::history::History;
+use wallbash::image::RawImage;
+use wallbash::output::{write_atomic, Backups};
+use wallbash::template::Engine;
+
+/// Marker used in place of a filepath to read stdin or write stdout
+const STDIO: &str = "-";
+/// Backup name prefix of palette exports (followed by the format)
+const EXPORT_BACKUP_PREFIX: &str = "exports/";
+
+/// Read file content or stdin when the path is `-`
+fn read_input(path: &str) -> Result<Vec<u8>> {
+    match path {
+        STDIO => {
+            let mut content = vec![];
+            std::io::stdin()
+                .read_to_end(&mut content)
+                .context("failed to read stdin")?;
+            Ok(content)
+        }
+        path => std::fs::read(path).context("file read failed"),
+    }
+}
+
+fn parse_palette(palette: &str, content: &[u8]) -> Result<Palette> {
+    let format = PaletteFormat::detect(palette, content);
+    log::debug!("reading palette as {format}");
+    Ok(format.parse(palette, content)?)
+}
 
 fn read_palette(palette: &str) -> Result<Palette> {
     log::info!("reading palette from {:?}", palette);
-    let s = std::fs::read_to_string(palette).context("file read failed")?;
-    let p: Palette = toml::from_str(&s).context("deserialize failed")?;
-    Ok(p)
+    parse_palette(palette, &read_input(palette)?)
+}
+
+/// Read an image from a file or stdin when the path is `-`
+fn read_image(path: &str, size: Option<u32>) -> Result<RawImage> {
+    log::info!("reading image from {path:?}");
+    Ok(match path {
+        STDIO => RawImage::from_bytes(&read_input(path)?, size)?,
+        path => RawImage::new(path, size)?,
+    })
+}
+
+fn write_palette(palette: &Palette, output: &str, format: Option<PaletteFormat>) -> Result<()> {
+    let format = format
+        .or_else(|| PaletteFormat::from_path(output))
+        .unwrap_or(PaletteFormat::Toml);
+    let content = format.serialize(palette)?;
+    if output == STDIO {
+        let mut stdout = std::io::stdout();
+        stdout
+            .write_all(&content)
+            .context("failed to write stdout")?;
+        return stdout.flush().context("failed to write stdout");
+    }
+    log::info!("writing {format} palette to {output:?}");
+    write_atomic(Path::new(output), &content).context("failed to write palette file")
+}
+
+/// Generate palettes from an image or read an existing palette file
+fn load_palettes(path: &str, gradiant: Gradiant, size: Option<u32>) -> Result<PaletteSet> {
+    let img = match path {
+        STDIO => {
+            // stdin can only be read once so detect the content type from bytes
+            // (imghdr panics on input shorter than its signatures)
+            let content = read_input(path)?;
+            if ::image::guess_format(&content).is_err() {
+                return Ok(PaletteSet::new(parse_palette(path, &content)?)?);
+            }
+            RawImage::from_bytes(&content, size)
+        }
+        path => {
+            if imghdr::from_file(path)
+                .context("failed to detect file type")?
+                .is_none()
+            {
+                let palette = read_palette(path).context("failed to read palette file")?;
+                return Ok(PaletteSet::new(palette)?);
+            }
+            RawImage::new(path, size)
+        }
+    }
+    .context("failed to read base image")?;
+    let primaries = Primaries::new(&img).context("failed to analyze image")?;
+    Ok(PaletteSet::from_primaries(primaries, gradiant))
+}
+
+/// Generate palette from an image or read an existing palette file
+fn load_palette(path: &str, gradiant: Gradiant, size: Option<u32>) -> Result<Palette> {
+    let mut palettes = load_palettes(path, gradiant, size)?;
+    Ok(palettes.default_palette()?.clone())
+}
+
+/// Options selecting and overriding configuration
+#[derive(Debug, Args)]
+pub struct ConfigOpts {
+    /// Configuration source
+    #[clap(short, long)]
+    config: Option<String>,
+    /// Override a configuration key (e.g. templates.kitty.enabled=false)
+    #[clap(long = "set", value_name = "KEY=VALUE")]
+    sets: Vec<String>,
+    /// Configuration profile to activate
+    #[clap(short, long)]
+    profile: Option<String>,
+}
+
+impl ConfigOpts {
+    /// Collect `--set` overrides including the selected profile
+    fn sets(&self) -> Vec<String> {
+        let profile = self
+            .profile
+            .as_ref()
+            .map(|p| format!("profile = {}", toml::Value::from(p.as_str())));
+        self.sets.iter().cloned().chain(profile).collect()
+    }
+    /// Load configuration with all override layers applied
+    pub fn load(&self) -> Result<Config> {
+        Ok(Config::new(self.config.as_ref(), &self.sets())?)
+    }
+    /// Load configuration collecting every problem instead of failing
+    pub fn load_checked(&self) -> (Config, Vec<String>) {
+        Config::load_checked(self.config.as_ref(), &self.sets())
+    }
+    /// Load individual configuration layers
+    pub fn layers(&self) -> Result<Vec<Layer>> {
+        Ok(Config::layers(self.config.as_ref(), &self.sets())?)
+    }
 }
 
 #[derive(Debug, Parser)]
@@ -25,6 +153,33 @@ pub struct Cli {
     pub command: Commands,
 }
 
+/// Determine exit code from the first categorized error in the chain
+pub fn exit_code(err: &anyhow::Error) -> ExitCode {
+    let code = err
+        .chain()
+        .find_map(|e| e.downcast_ref::<Error>())
+        .map_or(1, |e| e.exit_code());
+    ExitCode::from(code)
+}
+
+/// Execute the parsed command
+pub fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Commands::Run(args) => args.run(),
+        Commands::Fill(args) => args.fill(),
+        Commands::Generate(args) => args.generate(),
+        Commands::Restore(args) => args.restore(),
+        Commands::History(args) => args.history(),
+        Commands::Undo(args) => args.undo(),
+        Commands::Preview(args) => args.preview(),
+        Commands::Swatch(args) => args.swatch(),
+        Commands::Config(args) => args.config(),
+        Commands::Convert(args) => args.convert(),
+        Commands::Templates(args) => args.templates(),
+        Commands::Transition(args) => args.transition(),
+    }
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     /// Generate a pallete from an image and apply
@@ -33,69 +188,245 @@ pub enum Commands {
     Fill(FillArgs),
     /// Generate a new Color Palette
     Generate(GenerateArgs),
+    /// Restore template targets from a previous run
+    Restore(RestoreArgs),
+    /// Inspect and re-apply previously applied palettes
+    History(HistoryArgs),
+    /// Re-apply the palette used before the most recent run
+    Undo(UndoArgs),
+    /// Preview palettes in the terminal
+    Preview(PreviewArgs),
+    /// Render a PNG swatch sheet of a palette
+    Swatch(SwatchArgs),
+    /// Validate configuration and export its schema
+    Config(ConfigArgs),
+    /// Convert a palette file between formats
+    Convert(ConvertArgs),
+    /// List and copy out built-in templates
+    Templates(TemplatesArgs),
+    /// Fade between two palettes in interpolated steps
+    Transition(TransitionArgs),
 }
 
 #[derive(Debug, Args)]
 pub struct RunArgs {
+    /// Wallpaper or palette file (`-` for stdin)
     image: String,
-    /// Configuration source
-    #[clap(short, long)]
-    config: Option<String>,
+    #[clap(flatten)]
+    config: ConfigOpts,
     /// Default Gradiant to use
     #[clap(short, long)]
     gradiant: Option<Gradiant>,
+    /// Active color-mode (dark, light, auto)
+    #[clap(short, long)]
+    theme: Option<ThemeMode>,
     /// Shrink Image to Dimension before Analysis
     #[clap(short, long)]
     size: Option<u32>,
 }
 
 impl RunArgs {
-    fn get_palette(&self, gradiant: Gradiant) -> Result<Palette> {
-        match imghdr::from_file(&self.image).context("failed to detect file type")? {
-            Some(_) => {
-                let img =
-                    RawImage::new(&self.image, self.size).context("failed to read base image")?;
-                Ok(Palette::create(&img, gradiant))
-            }
-            None => read_palette(&self.image).context("failed to read palette file"),
-        }
-    }
     pub fn run(self) -> Result<()> {
         // read config
-        let config = Config::new(self.config.as_ref())?;
-        if config.templates.is_empty() {
-            log::error!("no templates in config. no actions to complete!");
-            return Err(anyhow::anyhow!("no templates in configuration"));
+        let mut config = self.config.load()?;
+        if let Some(theme) = self.theme {
+            config.theme = theme;
         }
         // read/generate palette
-        let gradiant = self.gradiant.clone().unwrap_or(config.gradiant);
-        let palette = self.get_palette(gradiant)?;
-        // iterate templates and fill in palette information
-        for (name, cfg) in config.templates {
-            let target = shellexpand::tilde(&cfg.target).to_string();
-            log::info!("writing template {name:?} {:?} => {target:?}", cfg.template);
+        let gradiant = self.gradiant.clone().unwrap_or(config.gradiant.clone());
+        let mut palettes = load_palettes(&self.image, gradiant, self.size)?;
+        apply_palette(&config, &mut palettes)?;
+        record_history(palettes.default_palette()?.clone())
+    }
+}
+
+/// Render all configured templates using the specified palettes
+fn apply_palette(config: &Config, palettes: &mut PaletteSet) -> Result<()> {
+    prepare_palettes(config, palettes)?;
+    let templates = load_templates(config, &[palettes.theme()])?;
+    let mut renderer = Renderer::new(config, &templates)?;
+    let selected = select_palettes(palettes, &renderer.gradiants())?;
+    renderer.render(&selected)
+}
+
+/// Apply configured variations, color-mode and slot overrides to palettes
+fn prepare_palettes(config: &Config, palettes: &mut PaletteSet) -> Result<()> {
+    palettes.set_variations(config.variation.clone());
+    palettes.set_theme(config.theme.theme())?;
+    palettes
+        .set_overrides(config.overrides.iter())
+        .context("invalid palette override")
+}
+
+/// Generate the palette of each gradiant (`None` for the default palette)
+fn select_palettes(
+    palettes: &mut PaletteSet,
+    gradiants: &[Option<Gradiant>],
+) -> Result<HashMap<Option<Gradiant>, Palette>> {
+    let mut selected = HashMap::new();
+    for gradiant in gradiants {
+        let palette = match gradiant.as_ref() {
+            Some(gradiant) => palettes.get(gradiant),
+            None => palettes.default_palette(),
+        }?;
+        selected.insert(gradiant.clone(), palette.clone());
+    }
+    Ok(selected)
+}
+
+/// Template source read for rendering into its target
+struct LoadedTemplate {
+    render: TemplateRender,
+    gradiant: Option<Gradiant>,
+    only_theme: Option<Theme>,
+    source: String,
+}
+
+/// Read every template rendered for at least one of the color-modes
+fn load_templates(config: &Config, themes: &[Theme]) -> Result<Vec<LoadedTemplate>> {
+    let mut templates = vec![];
+    for (name, cfg) in config.templates.iter() {
+        let mut reasons: Vec<String> = themes
+            .iter()
+            .filter_map(|theme| cfg.skip_reason(*theme))
+            .collect();
+        if reasons.len() == themes.len() {
+            reasons.dedup();
+            log::debug!("skipping template {name:?}: {}", reasons.join(", "));
+            continue;
+        }
+        for render in cfg.expand(name)? {
+            let source = builtin::read_template(&render.template).context(format!(
+                "{:?} failed to read template {:?}",
+                render.name, render.template
+            ))?;
+            templates.push(LoadedTemplate {
+                render,
+                gradiant: cfg.gradiant.clone(),
+                only_theme: cfg.only_theme,
+                source,
+            });
+        }
+    }
+    Ok(templates)
+}
+/// Configured exports and templates rendered from palettes
+struct Renderer<'a> {
+    config: &'a Config,
+    templates: Vec<&'a LoadedTemplate>,
+    engine: Engine<'a>,
+    backups: Backups,
+    run: u128,
+    keep: usize,
+}
+
+impl<'a> Renderer<'a> {
+    /// Compile templates once for every following render
+    fn new(config: &'a Config, templates: &'a [LoadedTemplate]) -> Result<Self> {
+        if config.templates.is_empty() && config.exports.targets().is_empty() {
+            log::error!("no templates in config. no actions to complete!");
+            return Err(Error::NoTemplates.into());
+        }
+        let mut engine = Engine::new().with_vars(&config.vars);
+        let mut compiled = vec![];
+        for template in templates {
+            let TemplateRender { name, .. } = &template.render;
+            match engine.add_template(name, &template.source) {
+                Ok(()) => compiled.push(template),
+                Err(err) => log::warn!("{name:?} template compile failed: {err:?}"),
+            }
+        }
+        Ok(Self {
+            config,
+            templates: compiled,
+            engine,
+            backups: Backups::new()?,
+            run: Backups::run_id(),
+            keep: config.backups,
+        })
+    }
+    /// Gradiants of the palettes used by exports and templates
+    fn gradiants(&self) -> Vec<Option<Gradiant>> {
+        let mut gradiants = vec![None];
+        for template in self.templates.iter() {
+            if !gradiants.contains(&template.gradiant) {
+                gradiants.push(template.gradiant.clone());
+            }
+        }
+        gradiants
+    }
+    /// Write exports and templates using the palette of each gradiant
+    fn render(&mut self, palettes: &HashMap<Option<Gradiant>, Palette>) -> Result<()> {
+        for (format, path) in self.config.exports.targets().iter() {
+            log::info!("writing {format} export => {path:?}");
+            let content = format.serialize(&palettes[&None])?;
+            let path = Path::new(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).context("failed to make export dir")?;
+            }
+            // exports are restored together with the templates of the run
+            if self.keep > 0 {
+                let name = format!("{EXPORT_BACKUP_PREFIX}{format}");
+                if let Err(err) = self.backups.save(self.run, &name, path, self.keep) {
+                    log::warn!("{format} export backup failed: {err:?}");
+                    continue;
+                }
+            }
+            write_atomic(path, content).context(format!("failed to write {format} export"))?;
+        }
+        for template in self.templates.iter() {
+            let TemplateRender {
+                name,
+                template: source,
+                target,
+            } = &template.render;
+            let palette = &palettes[&template.gradiant];
+            if let Some(only) = template.only_theme.filter(|only| *only != palette.theme) {
+                log::debug!("skipping template {name:?}: only rendered for {only} theme");
+                continue;
+            }
+            log::info!("writing template {name:?} {source:?} => {target:?}");
             // generate directory for target
-            if let Some(parent) = PathBuf::from(&target).parent() {
+            if let Some(parent) = Path::new(target).parent() {
                 if !parent.is_dir() {
                     std::fs::create_dir_all(parent)
                         .context(format!("{name:?} failed to make template target dir"))?;
                 }
             }
             // render result to template target
-            let mut engine = Engine::new();
-            let base = std::fs::read_to_string(cfg.template).context("file read failed")?;
-            let result = match engine.render(&base, &palette) {
-                Ok(render) => std::fs::write(&target, render),
-                Err(err) => Ok(log::warn!("{name:?} template render failed: {err:?}")),
+            let render = match self.engine.render_template(name, palette) {
+                Ok(render) => render,
+                Err(err) => {
+                    log::warn!("{name:?} template render failed: {err:?}");
+                    continue;
+                }
             };
-            if let Err(err) = result {
+            if self.keep > 0 {
+                let saved = self
+                    .backups
+                    .save(self.run, name, Path::new(target), self.keep);
+                if let Err(err) = saved {
+                    log::warn!("{name:?} template backup failed: {err:?}");
+                    continue;
+                }
+            }
+            if let Err(err) = write_atomic(Path::new(target), &render) {
                 log::warn!("{name:?} template write failed: {err:?}");
             }
         }
+        // only back up targets as they were before the first render
+        self.keep = 0;
         Ok(())
     }
 }
 
+/// Record applied palette in the history store
+fn record_history(palette: Palette) -> Result<()> {
+    let mut history = History::load()?;
+    history.push(palette);
+    history.save()
+}
+
 #[derive(Debug, Args)]
 pub struct FillArgs {
     /// Template to render
@@ -103,6 +434,9 @@ pub struct FillArgs {
     /// Palette definition used to render template
     #[clap(short, long, default_value = "colors.toml")]
     palette: String,
+    /// Active color-mode (dark, light, auto)
+    #[clap(short, long, value_enum, default_value_t)]
+    theme: ThemeMode,
     /// Output
     #[clap(short, long)]
     output: Option<String>,
@@ -112,7 +446,7 @@ impl FillArgs {
     fn read_template(&self) -> Result<String> {
         if let Some(template) = self.template.as_ref() {
             log::info!("reading template from {template:?}");
-            return Ok(std::fs::read_to_string(template).context("file read failed")?);
+            return std::fs::read_to_string(template).context("file read failed");
         }
         log::info!("reading template from stdin");
         let mut template = String::new();
@@ -123,14 +457,24 @@ impl FillArgs {
         Ok(template)
     }
     pub fn fill(self) -> Result<()> {
-        let palette = read_palette(&self.palette).context("failed to load palette")?;
+        if self.palette == STDIO && self.template.is_none() {
+            return Err(anyhow::anyhow!(
+                "palette and template cannot both be read from stdin"
+            ));
+        }
+        let mut palette = read_palette(&self.palette).context("failed to load palette")?;
+        if let Some(theme) = self.theme.theme() {
+            palette.set_theme(theme)?;
+        }
         let template = self.read_template().context("failed to read template")?;
-        let mut engine = crate::template::Engine::new();
+        let mut engine = Engine::new();
         let result = engine
             .render(&template, &palette)
             .context("render failed")?;
         match self.output {
-            Some(output) => std::fs::write(output, result).context("failed to write output")?,
+            Some(output) => {
+                write_atomic(Path::new(&output), &result).context("failed to write output")?
+            }
             None => println!("{result}"),
         }
         Ok(())
@@ -139,25 +483,480 @@ impl FillArgs {
 
 #[derive(Debug, Args)]
 pub struct GenerateArgs {
-    /// Filepath of Wallpaper
-    path: String,
+    /// Filepath of Wallpaper (`-` for stdin)
+    #[clap(required_unless_present_any = ["from_color", "from_colors"])]
+    path: Option<String>,
+    /// Generate palette from a seed color instead of an image
+    #[clap(long, conflicts_with_all = ["path", "from_colors"])]
+    from_color: Option<Color>,
+    /// Generate palette from up to four seed colors instead of an image
+    #[clap(long, num_args = 1..=4, conflicts_with = "path")]
+    from_colors: Vec<Color>,
+    /// Harmony rule deriving missing or substituted colors
+    /// (analogous, triadic, complementary, split-complementary)
+    #[clap(long)]
+    harmony: Option<Harmony>,
+    /// Substitute primaries closer than this CIEDE2000 distance with harmony hues
+    /// (the config `[variation]` table is not read by generate)
+    #[clap(long)]
+    min_delta_e: Option<f32>,
     /// Shrink Image to Dimension before Analysis
     #[clap(short, long)]
     size: Option<u32>,
     /// Color Pallete Asignment
     #[clap(short, long, default_value = "auto")]
     gradiant: Gradiant,
-    /// Output for Palette
+    /// Active color-mode (dark, light, auto)
+    #[clap(short, long, value_enum, default_value_t)]
+    theme: ThemeMode,
+    /// Output for Palette (`-` for stdout)
     #[clap(short, long, default_value = "./colors.toml")]
     output: String,
+    /// Palette format (toml, json, yaml, pywal, gpl, ase, kpl). Guessed from output by default
+    #[clap(short, long)]
+    format: Option<PaletteFormat>,
+    /// Write a PNG swatch sheet of the palette
+    #[clap(long)]
+    preview: Option<PathBuf>,
 }
 
 impl GenerateArgs {
     pub fn generate(self) -> Result<()> {
-        let img = RawImage::new(&self.path, self.size).context("failed to load image")?;
-        let palette = Palette::create(&img, self.gradiant);
-        let content = toml::to_string(&palette).context("failed to serialize palette")?;
-        let mut f = std::fs::File::create(&self.output).context("failed to create palette file")?;
-        write!(f, "{content}").context("failed to write to palette file")
+        let primaries = match self.path.as_ref() {
+            Some(path) => {
+                let img = read_image(path, self.size).context("failed to load image")?;
+                Primaries::new(&img)
+            }
+            None => {
+                let seeds: Vec<Color> = self
+                    .from_color
+                    .into_iter()
+                    .chain(self.from_colors)
+                    .collect();
+                Primaries::from_seeds(&seeds, self.harmony.unwrap_or_default())
+            }
+        };
+        let mut primaries = primaries?;
+        if let Some(min_delta_e) = self.min_delta_e {
+            let default = Variation::default();
+            let variation = Variation {
+                min_delta_e,
+                harmony: self.harmony.unwrap_or(default.harmony),
+            };
+            primaries = primaries.vary(&variation);
+        }
+        let mut palette = Palette::from_primaries(&primaries, self.gradiant)
+            .context("failed to generate palette")?;
+        if let Some(theme) = self.theme.theme() {
+            palette.set_theme(theme)?;
+        }
+        if let Some(preview) = self.preview.as_ref() {
+            wallbash::preview::save_sheet(&palette, preview)?;
+        }
+        write_palette(&palette, &self.output, self.format)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct RestoreArgs {
+    /// Run to restore templates from (defaults to most recent)
+    run: Option<u128>,
+    /// List runs with available backups
+    #[clap(short, long)]
+    list: bool,
+}
+
+impl RestoreArgs {
+    pub fn restore(self) -> Result<()> {
+        let backups = Backups::new()?;
+        let runs = backups.runs().context("failed to list backups")?;
+        if self.list {
+            runs.iter().for_each(|run| println!("{run}"));
+            return Ok(());
+        }
+        let run = match self.run {
+            Some(run) => run,
+            None => *runs.first().context("no backups available")?,
+        };
+        log::info!("restoring templates from run {run}");
+        backups.restore(run)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct HistoryArgs {
+    #[clap(subcommand)]
+    command: HistoryCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommands {
+    /// List previously applied palettes (newest first)
+    List,
+    /// Re-apply a palette from history
+    Apply(HistoryApplyArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct HistoryApplyArgs {
+    /// History index to apply (zero is the most recent)
+    index: usize,
+    #[clap(flatten)]
+    config: ConfigOpts,
+}
+
+impl HistoryArgs {
+    pub fn history(self) -> Result<()> {
+        let history = History::load()?;
+        match self.command {
+            HistoryCommands::List => {
+                for (n, entry) in history.entries.iter().enumerate() {
+                    let p = &entry.palette;
+                    println!(
+                        "{n}\t{}\t{}\t{}\t{}",
+                        entry.time(),
+                        p.theme,
+                        p.gradiant,
+                        p.file
+                    );
+                }
+                Ok(())
+            }
+            HistoryCommands::Apply(args) => {
+                let config = args.config.load()?;
+                let palette = history.get(args.index)?.palette.clone();
+                log::info!("applying palette from history: {:?}", palette.file);
+                apply_palette(&config, &mut PaletteSet::new(palette.clone())?)?;
+                record_history(palette)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct UndoArgs {
+    #[clap(flatten)]
+    config: ConfigOpts,
+}
+
+impl UndoArgs {
+    pub fn undo(self) -> Result<()> {
+        let config = self.config.load()?;
+        let mut history = History::load()?;
+        let palette = history.undo()?.palette.clone();
+        log::info!("restoring previous palette: {:?}", palette.file);
+        apply_palette(&config, &mut PaletteSet::new(palette)?)?;
+        history.save()
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct PreviewArgs {
+    /// Images or palette files to preview (two are shown side by side)
+    #[clap(required = true, num_args = 1..=2)]
+    paths: Vec<String>,
+    /// Gradiants used for image palettes (two compare a single image)
+    #[clap(short, long, num_args = 1..=2)]
+    gradiant: Vec<Gradiant>,
+    /// Shrink Image to Dimension before Analysis
+    #[clap(short, long)]
+    size: Option<u32>,
+}
+
+impl PreviewArgs {
+    pub fn preview(self) -> Result<()> {
+        let count = self.paths.len().max(self.gradiant.len());
+        // analyze each path once (stdin can only be read once) and share it across columns
+        let mut sets: HashMap<&str, PaletteSet> = HashMap::new();
+        let mut palettes = vec![];
+        for n in 0..count {
+            let path = self
+                .paths
+                .get(n)
+                .or(self.paths.last())
+                .expect("missing path");
+            let gradiant = self
+                .gradiant
+                .get(n)
+                .or(self.gradiant.last())
+                .cloned()
+                .unwrap_or_default();
+            let set = match sets.entry(path) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    entry.insert(load_palettes(path, gradiant.clone(), self.size)?)
+                }
+            };
+            let palette = set.get(&gradiant).context("failed to generate palette")?;
+            palettes.push(wallbash::preview::render(palette));
+        }
+        for line in wallbash::preview::columns(palettes) {
+            println!("{line}");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct SwatchArgs {
+    /// Image or palette file to render
+    path: String,
+    /// Color Pallete Asignment
+    #[clap(short, long, default_value = "auto")]
+    gradiant: Gradiant,
+    /// Shrink Image to Dimension before Analysis
+    #[clap(short, long)]
+    size: Option<u32>,
+    /// Output for swatch sheet
+    #[clap(short, long, default_value = "./colors.png")]
+    output: PathBuf,
+}
+
+impl SwatchArgs {
+    pub fn swatch(self) -> Result<()> {
+        let palette = load_palette(&self.path, self.gradiant, self.size)?;
+        wallbash::preview::save_sheet(&palette, &self.output)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    #[clap(subcommand)]
+    command: ConfigCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// Check configuration and templates for problems
+    Check(ConfigLoadArgs),
+    /// Print JSON Schema of the configuration file
+    Schema,
+    /// Print effective configuration and where each value came from
+    Show(ConfigLoadArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigLoadArgs {
+    #[clap(flatten)]
+    config: ConfigOpts,
+}
+
+impl ConfigArgs {
+    pub fn config(self) -> Result<()> {
+        match self.command {
+            ConfigCommands::Check(args) => {
+                let (config, problems) = args.config.load_checked();
+                if problems.is_empty() {
+                    println!("config ok ({} templates)", config.templates.len());
+                    return Ok(());
+                }
+                for problem in problems.iter() {
+                    println!("{problem}");
+                }
+                Err(Error::ConfigProblems(problems.len()).into())
+            }
+            ConfigCommands::Show(args) => {
+                let layers = args.config.layers()?;
+                let config = Config::from_layers(&layers)?;
+                for (key, value, source) in config.origins(&layers)? {
+                    println!("{key} = {value}  # {source}");
+                }
+                Ok(())
+            }
+            ConfigCommands::Schema => {
+                let schema = schemars::schema_for!(Config);
+                let content =
+                    serde_json::to_string_pretty(&schema).context("failed to serialize schema")?;
+                println!("{content}");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    /// Palette file to convert (`-` for stdin)
+    input: String,
+    /// Output for Palette (`-` for stdout)
+    #[clap(short, long, default_value = "-")]
+    output: String,
+    /// Palette format (toml, json, yaml, pywal, gpl, ase, kpl). Guessed from output by default
+    #[clap(short, long)]
+    format: Option<PaletteFormat>,
+}
+
+impl ConvertArgs {
+    pub fn convert(self) -> Result<()> {
+        let palette = read_palette(&self.input).context("failed to read palette")?;
+        write_palette(&palette, &self.output, self.format)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct TemplatesArgs {
+    #[clap(subcommand)]
+    command: TemplatesCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TemplatesCommands {
+    /// List built-in templates and their default targets
+    List,
+    /// Write a built-in template out for customization
+    Dump(TemplatesDumpArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct TemplatesDumpArgs {
+    /// Built-in template name (e.g. kitty)
+    name: String,
+    /// Output for Template (`-` for stdout)
+    #[clap(short, long, default_value = "-")]
+    output: String,
+}
+
+impl TemplatesArgs {
+    pub fn templates(self) -> Result<()> {
+        match self.command {
+            TemplatesCommands::List => {
+                for builtin in BUILTINS {
+                    println!("{}\t{}", builtin.name, builtin.target);
+                }
+                Ok(())
+            }
+            TemplatesCommands::Dump(args) => {
+                let builtin = builtin::get(&args.name)?;
+                if args.output == STDIO {
+                    print!("{}", builtin.template);
+                    return std::io::stdout().flush().context("failed to write stdout");
+                }
+                log::info!("writing template {:?} to {:?}", builtin.name, args.output);
+                write_atomic(Path::new(&args.output), builtin.template)
+                    .context("failed to write template")
+            }
+        }
+    }
+}
+
+/// Parse a duration in seconds or milliseconds such as `500ms` or `1.5s`
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (value, scale) = match s.strip_suffix("ms") {
+        Some(value) => (value, 1000.0),
+        None => (s.strip_suffix('s').unwrap_or(s), 1.0),
+    };
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|value| Duration::try_from_secs_f64(value / scale).ok())
+        .ok_or_else(|| format!("invalid duration: {s:?} (expected e.g. 500ms or 1.5s)"))
+}
+
+#[derive(Debug, Args)]
+pub struct TransitionArgs {
+    /// Wallpaper or palette file to start from (`-` for stdin)
+    from: String,
+    /// Wallpaper or palette file to end at (`-` for stdin)
+    to: String,
+    #[clap(flatten)]
+    config: ConfigOpts,
+    /// Number of interpolated palettes applied (the last is the target palette)
+    #[clap(short = 'n', long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    steps: u32,
+    /// Total duration of the transition (e.g. 500ms, 2s)
+    #[clap(short, long, default_value = "1s", value_parser = parse_duration)]
+    duration: Duration,
+    /// Default Gradiant to use
+    #[clap(short, long)]
+    gradiant: Option<Gradiant>,
+    /// Active color-mode (dark, light, auto)
+    #[clap(short, long)]
+    theme: Option<ThemeMode>,
+    /// Shrink Image to Dimension before Analysis
+    #[clap(short, long)]
+    size: Option<u32>,
+    /// Print interpolated palettes to stdout as JSON lines instead of rendering templates
+    #[clap(long)]
+    json: bool,
+}
+
+impl TransitionArgs {
+    /// Load the palettes of a transition endpoint
+    fn endpoint(&self, path: &str, config: &Config) -> Result<PaletteSet> {
+        let gradiant = self.gradiant.clone().unwrap_or(config.gradiant.clone());
+        let mut palettes = load_palettes(path, gradiant, self.size)?;
+        prepare_palettes(config, &mut palettes)?;
+        Ok(palettes)
+    }
+    pub fn transition(self) -> Result<()> {
+        if self.from == STDIO && self.to == STDIO {
+            return Err(anyhow::anyhow!("only one palette can be read from stdin"));
+        }
+        let mut config = self.config.load()?;
+        if let Some(theme) = self.theme {
+            config.theme = theme;
+        }
+        let mut from = self
+            .endpoint(&self.from, &config)
+            .context("failed to load starting palette")?;
+        let mut to = self
+            .endpoint(&self.to, &config)
+            .context("failed to load target palette")?;
+        // read and compile templates once for every step
+        let templates = match self.json {
+            true => vec![],
+            false => load_templates(&config, &[from.theme(), to.theme()])?,
+        };
+        let mut renderer = match self.json {
+            true => None,
+            false => Some(Renderer::new(&config, &templates)?),
+        };
+        let gradiants = renderer.as_ref().map_or(vec![None], |r| r.gradiants());
+        let from = select_palettes(&mut from, &gradiants)?;
+        let to = select_palettes(&mut to, &gradiants)?;
+        let delay = self.duration / self.steps;
+        let mut stdout = std::io::stdout();
+        for step in 1..=self.steps {
+            let t = step as f32 / self.steps as f32;
+            let palettes: HashMap<Option<Gradiant>, Palette> = gradiants
+                .iter()
+                .map(|g| (g.clone(), from[g].mix(&to[g], t)))
+                .collect();
+            match renderer.as_mut() {
+                Some(renderer) => {
+                    log::info!("applying transition step {step}/{}", self.steps);
+                    renderer.render(&palettes)?;
+                }
+                None => {
+                    let line = serde_json::to_string(&palettes[&None])
+                        .context("failed to serialize palette")?;
+                    writeln!(stdout, "{line}")
+                        .and_then(|_| stdout.flush())
+                        .context("failed to write stdout")?;
+                }
+            }
+            if step < self.steps {
+                std::thread::sleep(delay);
+            }
+        }
+        match self.json {
+            true => Ok(()),
+            false => record_history(to[&None].clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+        assert!(parse_duration("2parsecs").is_err());
+        assert!(parse_duration("-1s").is_err());
     }
 }
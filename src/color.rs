@@ -1,12 +1,24 @@
 //! Color Operations
 
-use std::{fmt::Display, str::FromStr};
+use std::{borrow::Cow, collections::HashMap, fmt::Display, str::FromStr};
 
-use anyhow::{anyhow, Context, Result};
-use palette::{FromColor, Hsl, Hsv, IntoColor, Saturate, SetHue, ShiftHue, Srgb};
-use serde::{de::Error, Deserialize, Serialize};
+use clap::ValueEnum;
+use palette::{
+    color_difference::Ciede2000, FromColor, Hsl, Hsv, IntoColor, Lab, Mix, Oklab, Saturate, SetHue,
+    ShiftHue, Srgb,
+};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{de::Error as _, Deserialize, Serialize};
 
+use crate::error::{self, Error, Result};
+use crate::harmony::{Harmony, Variation};
 use crate::image::RawImage;
+use crate::material::Material;
+
+/// Hue rotation step (degrees) used when harmony hues are exhausted
+const HUE_STEP: f32 = 30.0;
+/// Number of primary colors in a palette
+pub const PRIMARY_COLORS: usize = 4;
 
 const GRADIANT_STD: [(u8, u8); 9] = [
     (32, 50),
@@ -53,83 +65,662 @@ const GRADIANT_MONO: [(u8, u8); 9] = [
     (99, 0),
 ];
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Primary color with its generated text color and accent gradiant
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaletteColor {
     pub primary: Color,
     pub text: Color,
     pub accents: [Color; 9],
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl PaletteColor {
+    /// Interpolate primary, text and accent colors towards another palette color
+    pub fn mix(&self, other: &Self, t: f32) -> Self {
+        Self {
+            primary: self.primary.mix(&other.primary, t),
+            text: self.text.mix(&other.text, t),
+            accents: std::array::from_fn(|n| self.accents[n].mix(&other.accents[n], t)),
+        }
+    }
+}
+
+/// Palette colors generated for a single color-mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variant {
+    pub color0: PaletteColor,
+    pub color1: PaletteColor,
+    pub color2: PaletteColor,
+    pub color3: PaletteColor,
+    /// Palette colors whose primary was synthesized for hue variation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub synthesized: Vec<String>,
+}
+
+impl Variant {
+    /// Generate text/accent colors for primaries sorted by color-mode
+    fn new(primaries: &Primaries, gradiant: &Gradiant) -> error::Result<Self> {
+        let mut palettes = vec![];
+        for color in primaries.colors.iter().cloned() {
+            let dark = color.luminocity() < 0.5;
+            // determine text color
+            let text_base = color.negative();
+            let text_bright = if dark { 188 } else { 16 };
+            let text_color = text_base.modulate(text_bright, 10, 100);
+            // generate accent colors
+            let accents = gradiant.gradiant().map(|(brightness, saturation)| {
+                let sv = saturation as f32 / 100.0;
+                let bv = brightness as f32 / 100.0;
+                color.accent(sv, bv)
+            });
+            palettes.push(PaletteColor {
+                primary: color,
+                text: text_color,
+                accents,
+            })
+        }
+        let found = palettes.len();
+        let [color0, color1, color2, color3] =
+            palettes.try_into().map_err(|_| Error::NotEnoughColors {
+                expected: PRIMARY_COLORS,
+                found,
+            })?;
+        Ok(Self {
+            color0,
+            color1,
+            color2,
+            color3,
+            synthesized: primaries
+                .synthesized
+                .iter()
+                .map(|n| format!("color{n}"))
+                .collect(),
+        })
+    }
+    /// Interpolate colors towards another variant
+    fn mix(&self, other: &Self, t: f32) -> Self {
+        Self {
+            color0: self.color0.mix(&other.color0, t),
+            color1: self.color1.mix(&other.color1, t),
+            color2: self.color2.mix(&other.color2, t),
+            color3: self.color3.mix(&other.color3, t),
+            synthesized: other.synthesized.clone(),
+        }
+    }
+    /// Copy the active colors of a palette
+    fn from_palette(palette: &Palette) -> Self {
+        Self {
+            color0: palette.color0.clone(),
+            color1: palette.color1.clone(),
+            color2: palette.color2.clone(),
+            color3: palette.color3.clone(),
+            synthesized: palette.synthesized.clone(),
+        }
+    }
+    /// Mutable palette color by index
+    fn color_mut(&mut self, index: usize) -> &mut PaletteColor {
+        match index {
+            0 => &mut self.color0,
+            1 => &mut self.color1,
+            2 => &mut self.color2,
+            _ => &mut self.color3,
+        }
+    }
+}
+
+/// Four palette colors generated from an image, sorted by color-mode
+///
+/// `color0`..`color3` hold the active color-mode while `dark` and `light`
+/// hold both variants generated from the same primary colors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Palette {
     pub file: String,
-    pub theme: String,
+    pub theme: Theme,
     pub gradiant: Gradiant,
     pub color0: PaletteColor,
     pub color1: PaletteColor,
     pub color2: PaletteColor,
     pub color3: PaletteColor,
+    /// Palette colors whose primary was synthesized for hue variation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub synthesized: Vec<String>,
+    /// Material 3 schemes generated from the dominant color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub material: Option<Material>,
+    /// Colors generated for the dark color-mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dark: Option<Variant>,
+    /// Colors generated for the light color-mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub light: Option<Variant>,
 }
 
-impl Palette {
-    /// Generate Palette of Colors with Specified Gradiant
-    pub fn create(image: &RawImage, mut gradiant: Gradiant) -> Self {
+/// Primary colors extracted from an image shared between palette gradiants
+#[derive(Debug, Clone)]
+pub struct Primaries {
+    file: String,
+    theme: Theme,
+    /// Gradiant selected when [`Gradiant::Auto`] is requested
+    auto: Gradiant,
+    colors: Vec<Color>,
+    /// Color of the largest image cluster when known
+    dominant: Option<Color>,
+    /// Indices of colors synthesized by [`Primaries::vary`]
+    synthesized: Vec<usize>,
+}
+
+impl Primaries {
+    /// Calculate sorted primary colors and color-mode of an image
+    pub fn new(image: &RawImage) -> error::Result<Self> {
         log::info!("calculating primary colors");
-        let mut colors = image.kmeans(4);
-        let mut sort_mode = "dark";
+        let clusters = image.clusters(PRIMARY_COLORS)?;
+        let dominant = clusters
+            .iter()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(c, _)| c.clone());
+        let mut colors: Vec<Color> = clusters.into_iter().map(|(c, _)| c).collect();
+        let mut sort_mode = Theme::Dark;
 
         colors.sort();
         if image.mean_luminocity() > 0.5 {
-            sort_mode = "light";
+            sort_mode = Theme::Light;
             colors.reverse()
         }
-        log::info!("determined color-mode: {sort_mode:?}");
+        log::info!("determined color-mode: {sort_mode}");
 
+        let auto = match image.mean_saturation() < 0.12 {
+            true => Gradiant::Mono,
+            false => Gradiant::Standard,
+        };
+        let file = image.path().to_string_lossy();
+        Ok(Self {
+            file: file.as_ref().to_owned(),
+            theme: sort_mode,
+            auto,
+            colors,
+            dominant,
+            synthesized: vec![],
+        })
+    }
+    /// Build primary colors from an arbitrary list of colors
+    pub fn from_colors(file: &str, mut colors: Vec<Color>) -> Self {
+        colors.sort();
+        let mean = colors.iter().map(|c| c.luminocity()).sum::<f32>() / colors.len() as f32;
+        let theme = match mean > 0.5 {
+            true => Theme::Light,
+            false => Theme::Dark,
+        };
+        if theme == Theme::Light {
+            colors.reverse();
+        }
+        Self {
+            file: file.to_owned(),
+            theme,
+            auto: Gradiant::Standard,
+            colors,
+            dominant: None,
+            synthesized: vec![],
+        }
+    }
+    /// Build primary colors from up to four seed colors
+    ///
+    /// Missing primaries are derived from the first seed using the harmony rule.
+    pub fn from_seeds(seeds: &[Color], harmony: Harmony) -> error::Result<Self> {
+        let Some(seed) = seeds.first() else {
+            return Err(Error::NoSeeds);
+        };
+        if seeds.len() > PRIMARY_COLORS {
+            return Err(Error::TooManySeeds {
+                max: PRIMARY_COLORS,
+                found: seeds.len(),
+            });
+        }
+        let mut colors = seeds.to_vec();
+        let missing = PRIMARY_COLORS - seeds.len();
+        colors.extend(harmony.derive(seed).into_iter().take(missing));
+        let file: Vec<String> = seeds.iter().map(|c| c.to_string()).collect();
+        log::info!("generating {harmony} colors from seeds {file:?}");
+        let mut primaries = Self::from_colors(&file.join(","), colors);
+        primaries.dominant = Some(seed.clone());
+        Ok(primaries)
+    }
+    /// Reuse primary colors of an existing palette
+    pub fn from_palette(palette: &Palette) -> Self {
+        Self {
+            file: palette.file.clone(),
+            theme: palette.theme,
+            auto: palette.gradiant.clone(),
+            colors: palette.colors().map(|c| c.primary.clone()).to_vec(),
+            dominant: None,
+            synthesized: (0..PRIMARY_COLORS)
+                .filter(|n| palette.synthesized.contains(&format!("color{n}")))
+                .collect(),
+        }
+    }
+    /// Primary colors sorted for the given color-mode
+    ///
+    /// The same colors are reused and only their order changes: dark palettes
+    /// start with the darkest color and light palettes with the lightest.
+    pub fn with_theme(&self, theme: Theme) -> Self {
+        let mut colors: Vec<(Color, bool)> = self
+            .colors
+            .iter()
+            .enumerate()
+            .map(|(n, color)| (color.clone(), self.synthesized.contains(&n)))
+            .collect();
+        colors.sort_by(|(a, _), (b, _)| a.cmp(b));
+        if theme == Theme::Light {
+            colors.reverse();
+        }
+        Self {
+            theme,
+            synthesized: (0..colors.len()).filter(|n| colors[*n].1).collect(),
+            colors: colors.into_iter().map(|(c, _)| c).collect(),
+            ..self.clone()
+        }
+    }
+    /// Primary colors sorted for the active color-mode
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+    /// Color-mode the primaries are sorted for
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+    /// Dominant image color, or the most saturated primary when unknown
+    pub fn dominant_color(&self) -> Color {
+        self.dominant
+            .clone()
+            .unwrap_or_else(|| most_saturated(&self.colors))
+    }
+    /// Substitute primaries closer than the minimum distance with harmony hues
+    ///
+    /// Colors are checked starting with the one closest to the dominant
+    /// color. Replacements rotate the dominant hue by the harmony rule and
+    /// keep the lightness of the color they replace. The result is sorted by
+    /// luminocity again since replacements may change perceived brightness.
+    pub fn vary(&self, variation: &Variation) -> Self {
+        let base = self.dominant_color();
+        let base_hsl: Hsl = base.0.into_color();
+        let mut order: Vec<usize> = (0..self.colors.len()).collect();
+        order.sort_by(|a, b| {
+            let (a, b) = (&self.colors[*a], &self.colors[*b]);
+            a.delta_e(&base).total_cmp(&b.delta_e(&base))
+        });
+        let mut varied = self.clone();
+        let mut accepted: Vec<Color> = vec![];
+        let distance = |color: &Color, accepted: &[Color]| {
+            accepted
+                .iter()
+                .map(|c| c.delta_e(color))
+                .fold(f32::INFINITY, f32::min)
+        };
+        for index in order {
+            let color = varied.colors[index].clone();
+            if distance(&color, &accepted) >= variation.min_delta_e {
+                accepted.push(color);
+                continue;
+            }
+            let hsl: Hsl = color.0.into_color();
+            let best = |rotations: Vec<f32>| {
+                rotations
+                    .into_iter()
+                    .map(|rotation| {
+                        let mut substitute = base_hsl.shift_hue(rotation);
+                        substitute.saturation = substitute.saturation.max(hsl.saturation);
+                        substitute.lightness = hsl.lightness;
+                        Color::from_color(substitute)
+                    })
+                    .max_by(|a, b| distance(a, &accepted).total_cmp(&distance(b, &accepted)))
+                    .unwrap_or(color.clone())
+            };
+            let mut substitute = best(variation.harmony.rotations());
+            if distance(&substitute, &accepted) < variation.min_delta_e {
+                // harmony hues are exhausted so search the whole color wheel
+                substitute = best((1..12).map(|n| n as f32 * HUE_STEP).collect());
+            }
+            log::info!("primary color{index} {color} too similar. using {substitute}");
+            accepted.push(substitute.clone());
+            varied.colors[index] = substitute;
+            varied.synthesized.push(index);
+        }
+        varied.with_theme(self.theme)
+    }
+}
+
+/// Select the most saturated color (black when empty)
+fn most_saturated<'a>(colors: impl IntoIterator<Item = &'a Color>) -> Color {
+    colors
+        .into_iter()
+        .max_by(|a, b| a.hsl().saturation.total_cmp(&b.hsl().saturation))
+        .cloned()
+        .unwrap_or(Color::from_rgb(0, 0, 0))
+}
+
+impl Palette {
+    /// Generate Palette of Colors with Specified Gradiant
+    pub fn create(image: &RawImage, gradiant: Gradiant) -> error::Result<Self> {
+        Self::from_primaries(&Primaries::new(image)?, gradiant)
+    }
+    /// Generate Palette of Colors from Precalculated Primary Colors
+    ///
+    /// Fails unless exactly four primary colors are given.
+    pub fn from_primaries(primaries: &Primaries, mut gradiant: Gradiant) -> error::Result<Self> {
+        if primaries.colors.len() != PRIMARY_COLORS {
+            return Err(Error::NotEnoughColors {
+                expected: PRIMARY_COLORS,
+                found: primaries.colors.len(),
+            });
+        }
         if gradiant == Gradiant::Auto {
-            if image.mean_saturation() < 0.12 {
+            if primaries.auto == Gradiant::Mono {
                 log::warn!("image saturation too low. reverting to mono palette");
-                gradiant = Gradiant::Mono;
-            } else {
-                gradiant = Gradiant::Standard;
             }
+            gradiant = primaries.auto.clone();
         }
 
         log::info!("rendering text/accent colors");
-        let mut palettes = vec![];
-        for color in colors {
-            let dark = color.luminocity() < 0.5;
-            // determine text color
-            let text_base = color.negative();
-            let text_bright = if dark { 188 } else { 16 };
-            let text_color = text_base.modulate(text_bright, 10, 100);
-            // generate accent colors
-            let mut accents = vec![];
-            for (brightness, saturation) in gradiant.gradiant() {
-                let sv = saturation as f32 / 100.0;
-                let bv = brightness as f32 / 100.0;
-                let accent = color.accent(sv, bv);
-                accents.push(accent);
+        let dark = Variant::new(&primaries.with_theme(Theme::Dark), &gradiant)?;
+        let light = Variant::new(&primaries.with_theme(Theme::Light), &gradiant)?;
+        let active = match primaries.theme {
+            Theme::Dark => dark.clone(),
+            Theme::Light => light.clone(),
+        };
+        Ok(Self {
+            file: primaries.file.clone(),
+            theme: primaries.theme,
+            gradiant,
+            color0: active.color0,
+            color1: active.color1,
+            color2: active.color2,
+            color3: active.color3,
+            synthesized: active.synthesized,
+            material: Some(Material::new(&primaries.dominant_color())),
+            dark: Some(dark),
+            light: Some(light),
+        })
+    }
+    /// Generate material schemes and color-mode variants for palettes read without them
+    ///
+    /// The active colors are kept as the variant of the palette color-mode.
+    pub fn fill_missing(&mut self) -> error::Result<()> {
+        if self.material.is_none() {
+            let source = most_saturated(self.colors().map(|c| &c.primary));
+            self.material = Some(Material::new(&source));
+        }
+        if self.dark.is_some() && self.light.is_some() {
+            return Ok(());
+        }
+        let primaries = Primaries::from_palette(self);
+        let active = Variant::from_palette(self);
+        for theme in [Theme::Dark, Theme::Light] {
+            let variant = match theme {
+                Theme::Dark => &mut self.dark,
+                Theme::Light => &mut self.light,
+            };
+            if variant.is_some() {
+                continue;
             }
-            palettes.push(PaletteColor {
-                primary: color,
-                text: text_color,
-                accents: accents.try_into().expect("not enough accents"),
-            })
+            *variant = Some(match primaries.theme == theme {
+                true => active.clone(),
+                false => Variant::new(&primaries.with_theme(theme), &self.gradiant)?,
+            });
         }
-        let file = image.path().to_string_lossy();
+        Ok(())
+    }
+    /// Make the variant of the given color-mode active
+    ///
+    /// Missing variants are generated first (see [`Palette::fill_missing`]).
+    pub fn set_theme(&mut self, theme: Theme) -> error::Result<()> {
+        self.fill_missing()?;
+        let variant = match theme {
+            Theme::Dark => self.dark.clone(),
+            Theme::Light => self.light.clone(),
+        };
+        let variant = variant.ok_or(Error::MissingVariant(theme))?;
+        self.theme = theme;
+        self.color0 = variant.color0;
+        self.color1 = variant.color1;
+        self.color2 = variant.color2;
+        self.color3 = variant.color3;
+        self.synthesized = variant.synthesized;
+        Ok(())
+    }
+    /// Interpolate palette colors towards another palette in Oklab
+    ///
+    /// `t` ranges from 0 (this palette) to 1 (the other palette). Colors,
+    /// variants and material schemes are interpolated while labels that
+    /// cannot be blended (file, theme, gradiant and synthesized colors) are
+    /// taken from the palette nearest to `t`.
+    pub fn mix(&self, other: &Self, t: f32) -> Self {
+        let nearest = if t < 0.5 { self } else { other };
+        let variant = |a: &Option<Variant>, b: &Option<Variant>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.mix(b, t)),
+            _ => match t < 0.5 {
+                true => a.clone(),
+                false => b.clone(),
+            },
+        };
         Self {
-            file: file.as_ref().to_owned(),
-            theme: sort_mode.to_owned(),
-            gradiant,
-            color3: palettes.pop().expect("not enough colors"),
-            color2: palettes.pop().expect("not enough colors"),
-            color1: palettes.pop().expect("not enough colors"),
-            color0: palettes.pop().expect("not enough colors"),
+            file: nearest.file.clone(),
+            theme: nearest.theme,
+            gradiant: nearest.gradiant.clone(),
+            color0: self.color0.mix(&other.color0, t),
+            color1: self.color1.mix(&other.color1, t),
+            color2: self.color2.mix(&other.color2, t),
+            color3: self.color3.mix(&other.color3, t),
+            synthesized: nearest.synthesized.clone(),
+            material: match (&self.material, &other.material) {
+                (Some(a), Some(b)) => Some(a.mix(b, t)),
+                _ => nearest.material.clone(),
+            },
+            dark: variant(&self.dark, &other.dark),
+            light: variant(&self.light, &other.light),
+        }
+    }
+    /// Iterate palette colors in order
+    pub fn colors(&self) -> [&PaletteColor; 4] {
+        [&self.color0, &self.color1, &self.color2, &self.color3]
+    }
+    /// Retrieve a palette slot by key (e.g. `color1.primary`, `color0.accents[4]`)
+    pub fn get(&self, key: &str) -> Result<&Color> {
+        let (index, slot) = parse_slot(key)?;
+        let palette = self.colors()[index];
+        Ok(match slot {
+            Slot::Primary => &palette.primary,
+            Slot::Text => &palette.text,
+            Slot::Accent(n) => &palette.accents[n],
+        })
+    }
+    /// Replace a palette slot by key (e.g. `color1.primary`, `color0.accents[4]`)
+    ///
+    /// The slot is replaced in the active colors and both color-mode variants.
+    pub fn set(&mut self, key: &str, color: Color) -> Result<()> {
+        let (index, slot) = parse_slot(key)?;
+        let palette = match index {
+            0 => &mut self.color0,
+            1 => &mut self.color1,
+            2 => &mut self.color2,
+            _ => &mut self.color3,
+        };
+        slot.set(palette, color.clone());
+        for variant in [self.dark.as_mut(), self.light.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            slot.set(variant.color_mut(index), color.clone());
         }
+        Ok(())
     }
 }
 
-#[derive(Debug)]
+/// Individual color within a palette color
+enum Slot {
+    Primary,
+    Text,
+    Accent(usize),
+}
+
+impl Slot {
+    /// Replace the slot color within a palette color
+    fn set(&self, palette: &mut PaletteColor, color: Color) {
+        let target = match self {
+            Self::Primary => &mut palette.primary,
+            Self::Text => &mut palette.text,
+            Self::Accent(n) => &mut palette.accents[*n],
+        };
+        *target = color;
+    }
+}
+
+/// Parse palette key into color index and slot
+fn parse_slot(key: &str) -> Result<(usize, Slot)> {
+    let invalid = |reason| Error::InvalidSlot {
+        key: key.to_owned(),
+        reason,
+    };
+    let (name, slot) = key
+        .split_once('.')
+        .ok_or_else(|| invalid("expected <color>.<slot>"))?;
+    let index = match name {
+        "color0" => 0,
+        "color1" => 1,
+        "color2" => 2,
+        "color3" => 3,
+        _ => return Err(invalid("unknown palette color")),
+    };
+    let slot = match slot {
+        "primary" => Slot::Primary,
+        "text" => Slot::Text,
+        slot => {
+            let n = slot
+                .strip_prefix("accents[")
+                .and_then(|s| s.strip_suffix(']'))
+                .and_then(|s| s.parse::<usize>().ok())
+                .ok_or_else(|| invalid("unknown palette slot"))?;
+            if n >= GRADIANT_STD.len() {
+                return Err(invalid("accent index out of range"));
+            }
+            Slot::Accent(n)
+        }
+    };
+    Ok((index, slot))
+}
+
+/// Palettes generated lazily for each gradiant from shared primary colors
+pub struct PaletteSet {
+    primaries: Primaries,
+    default: Gradiant,
+    theme: Option<Theme>,
+    overrides: Vec<(String, Color)>,
+    variations: HashMap<Gradiant, Variation>,
+    palettes: HashMap<Gradiant, Palette>,
+}
+
+impl PaletteSet {
+    /// Build palette set from primary colors with a default gradiant
+    pub fn from_primaries(primaries: Primaries, default: Gradiant) -> Self {
+        let default = match default {
+            Gradiant::Auto => primaries.auto.clone(),
+            default => default,
+        };
+        Self {
+            primaries,
+            default,
+            theme: None,
+            overrides: vec![],
+            variations: HashMap::new(),
+            palettes: HashMap::new(),
+        }
+    }
+    /// Build palette set around an existing default palette
+    pub fn new(mut palette: Palette) -> error::Result<Self> {
+        palette.fill_missing()?;
+        let primaries = Primaries::from_palette(&palette);
+        let default = palette.gradiant.clone();
+        let palettes = HashMap::from([(default.clone(), palette)]);
+        Ok(Self {
+            primaries,
+            default,
+            theme: None,
+            overrides: vec![],
+            variations: HashMap::new(),
+            palettes,
+        })
+    }
+    /// Pin palette slots to fixed colors in every generated palette
+    pub fn set_overrides<'b>(
+        &mut self,
+        overrides: impl IntoIterator<Item = (&'b String, &'b Color)>,
+    ) -> Result<()> {
+        self.default_palette()?;
+        for (key, color) in overrides {
+            for palette in self.palettes.values_mut() {
+                palette.set(key, color.clone())?;
+            }
+            self.overrides.push((key.clone(), color.clone()));
+        }
+        Ok(())
+    }
+    /// Enforce hue variation between primaries of generated palettes
+    ///
+    /// Variations are selected by gradiant, with the [`Gradiant::Auto`] entry
+    /// applying to gradiants without their own. Palettes generated before
+    /// this call, including a palette the set was built around with
+    /// [`PaletteSet::new`], are left unchanged.
+    pub fn set_variations(&mut self, variations: HashMap<Gradiant, Variation>) {
+        self.variations = variations;
+    }
+    /// Force the active color-mode of every palette
+    ///
+    /// `None` keeps the color-mode determined from the image.
+    pub fn set_theme(&mut self, theme: Option<Theme>) -> error::Result<()> {
+        self.theme = theme;
+        if let Some(theme) = self.theme {
+            for palette in self.palettes.values_mut() {
+                palette.set_theme(theme)?;
+            }
+        }
+        Ok(())
+    }
+    /// Color-mode of the palettes
+    pub fn theme(&self) -> Theme {
+        self.theme.unwrap_or(self.primaries.theme)
+    }
+    /// Retrieve the default palette
+    pub fn default_palette(&mut self) -> Result<&Palette> {
+        let default = self.default.clone();
+        self.get(&default)
+    }
+    /// Retrieve palette for gradiant, generating it when missing
+    pub fn get(&mut self, gradiant: &Gradiant) -> Result<&Palette> {
+        let gradiant = match gradiant {
+            Gradiant::Auto => self.primaries.auto.clone(),
+            gradiant => gradiant.clone(),
+        };
+        if !self.palettes.contains_key(&gradiant) {
+            let variation = self
+                .variations
+                .get(&gradiant)
+                .or_else(|| self.variations.get(&Gradiant::Auto));
+            let primaries = match variation {
+                Some(variation) => Cow::Owned(self.primaries.vary(variation)),
+                None => Cow::Borrowed(&self.primaries),
+            };
+            let mut palette = Palette::from_primaries(&primaries, gradiant.clone())?;
+            if let Some(theme) = self.theme {
+                palette.set_theme(theme)?;
+            }
+            for (key, color) in self.overrides.iter() {
+                palette.set(key, color.clone())?;
+            }
+            self.palettes.insert(gradiant.clone(), palette);
+        }
+        Ok(&self.palettes[&gradiant])
+    }
+}
+
+/// Srgb color serialized as a hex string (e.g. `#3A7BD5`)
+#[derive(Debug, Clone)]
 pub struct Color(pub Srgb<f32>);
 
 impl Color {
@@ -141,8 +732,8 @@ impl Color {
     }
     /// Convert value from Hex string
     pub fn from_hex(s: &str) -> Result<Self> {
-        let s = s.trim_start_matches("#");
-        let i = u32::from_str_radix(s, 16).context("invalid hex value")?;
+        let hex = s.trim_start_matches("#");
+        let i = u32::from_str_radix(hex, 16).map_err(|_| Error::InvalidColor(s.to_owned()))?;
         let rgb = Srgb::from(i);
         Ok(Self(rgb.into_format()))
     }
@@ -173,6 +764,26 @@ impl Color {
     pub fn luminocity(&self) -> f32 {
         0.2126 * self.0.red + 0.7152 * self.0.green + 0.0722 * self.0.blue
     }
+    /// Interpolate towards another color in Oklab (`t` from 0 to 1)
+    pub fn mix(&self, other: &Self, t: f32) -> Self {
+        let (a, b): (Oklab, Oklab) = (self.0.into_color(), other.0.into_color());
+        Self::from_color(a.mix(b, t))
+    }
+    /// Calculate CIEDE2000 perceptual distance between two colors
+    pub fn delta_e(&self, other: &Self) -> f32 {
+        let (a, b): (Lab, Lab) = (self.0.into_color(), other.0.into_color());
+        a.difference(b)
+    }
+    /// Calculate WCAG relative luminance of color
+    pub fn relative_luminance(&self) -> f32 {
+        let lin = self.0.into_linear();
+        0.2126 * lin.red + 0.7152 * lin.green + 0.0722 * lin.blue
+    }
+    /// Calculate WCAG contrast ratio between two colors
+    pub fn contrast(&self, other: &Self) -> f32 {
+        let (l1, l2) = (self.relative_luminance(), other.relative_luminance());
+        (l1.max(l2) + 0.05) / (l1.min(l2) + 0.05)
+    }
     /// Generate negative inverse of color
     pub fn negative(&self) -> Self {
         let (r, g, b) = self.rgb();
@@ -182,11 +793,11 @@ impl Color {
     /// Generate a new color by modulating Brightness/Saturation/Hue percentages
     pub fn modulate(&self, brightness: u8, saturation: u8, hue: u8) -> Self {
         let color = Color::from_color(if brightness == 100 {
-            self.0.clone()
+            self.0
         } else {
             // round(rgb * (BRIGHTNESS / 100.0))
             let value = brightness as f32 / 100.0;
-            let mut color = self.0.clone();
+            let mut color = self.0;
             color.red *= value;
             color.green *= value;
             color.blue *= value;
@@ -223,15 +834,13 @@ impl Eq for Color {}
 
 impl PartialOrd for Color {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.luminocity().partial_cmp(&other.luminocity())
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Color {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.luminocity()
-            .partial_cmp(&other.luminocity())
-            .expect("color ordering failed")
+        self.luminocity().total_cmp(&other.luminocity())
     }
 }
 
@@ -242,25 +851,26 @@ impl Display for Color {
 }
 
 impl FromStr for Color {
-    type Err = anyhow::Error;
+    type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.starts_with("#") {
-            return Ok(Self::from_hex(s)?);
+            return Self::from_hex(s);
         }
+        let invalid = || Error::InvalidColor(s.to_owned());
         if s.starts_with("rgb(") || s.starts_with("rgba(") {
-            let (_, v) = s.split_once("(").expect("missing open paren");
-            let (v, _) = v.split_once(")").context("missing rgb closing paren")?;
+            let (_, v) = s.split_once("(").ok_or_else(invalid)?;
+            let (v, _) = v.split_once(")").ok_or_else(invalid)?;
             let mut values = vec![];
             for i in v.split(",") {
-                let i = u8::from_str(i).context("invalid color u8")?;
+                let i = u8::from_str(i).map_err(|_| invalid())?;
                 values.push(i);
             }
             if values.len() < 3 {
-                return Err(anyhow!("rgb has too few arguments"));
+                return Err(invalid());
             }
             return Ok(Self::from_rgb(values[0], values[1], values[2]));
         }
-        Err(anyhow!("invalid color: {s:?}"))
+        Err(invalid())
     }
 }
 
@@ -284,9 +894,23 @@ impl<'de> Deserialize<'de> for Color {
     }
 }
 
+impl JsonSchema for Color {
+    fn schema_name() -> Cow<'static, str> {
+        "Color".into()
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "description": "hex (#RRGGBB) or rgb(r,g,b) color",
+        })
+    }
+}
+
 /// Supported Color Gradiants used for Color Generation
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub enum Gradiant {
+    /// Standard unless the image saturation is too low for color
+    #[default]
     Auto,
     Standard,
     Vibrant,
@@ -295,7 +919,8 @@ pub enum Gradiant {
 }
 
 impl Gradiant {
-    pub fn gradiant(&self) -> Vec<(u8, u8)> {
+    /// Accent (brightness, saturation) percentages from darkest to lightest
+    pub fn gradiant(&self) -> [(u8, u8); 9] {
         match self {
             Self::Auto => GRADIANT_STD,
             Self::Standard => GRADIANT_STD,
@@ -303,13 +928,6 @@ impl Gradiant {
             Self::Pastel => GRADIANT_PASTEL,
             Self::Mono => GRADIANT_MONO,
         }
-        .to_vec()
-    }
-}
-
-impl Default for Gradiant {
-    fn default() -> Self {
-        Self::Auto
     }
 }
 
@@ -362,3 +980,263 @@ impl<'de> Deserialize<'de> for Gradiant {
         Gradiant::from_str(&s).map_err(D::Error::custom)
     }
 }
+
+impl JsonSchema for Gradiant {
+    fn schema_name() -> Cow<'static, str> {
+        "Gradiant".into()
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "enum": ["auto", "standard", "vibrant", "pastel", "mono"],
+        })
+    }
+}
+
+/// Color-mode of palette colors
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Dark background with light text
+    #[default]
+    Dark,
+    /// Light background with dark text
+    Light,
+}
+
+impl Theme {
+    /// Lowercase name of the color-mode
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+        }
+    }
+}
+
+impl Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Selection of the active color-mode
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    /// Dark unless the image is mostly light
+    #[default]
+    Auto,
+    Dark,
+    Light,
+}
+
+impl ThemeMode {
+    /// Forced color-mode (none when automatic)
+    pub fn theme(&self) -> Option<Theme> {
+        match self {
+            Self::Auto => None,
+            Self::Dark => Some(Theme::Dark),
+            Self::Light => Some(Theme::Light),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Primary colors shared by the palette tests of all modules
+    pub(crate) fn primaries() -> Primaries {
+        let colors = ["#101820", "#3A7BD5", "#805010", "#F0F0F0"];
+        let colors = colors.map(|c| Color::from_hex(c).unwrap()).to_vec();
+        Primaries::from_colors("test", colors)
+    }
+
+    pub(crate) fn palette() -> Palette {
+        Palette::from_primaries(&primaries(), Gradiant::Standard).unwrap()
+    }
+
+    fn primary_hexes(variant: &Variant) -> [String; 4] {
+        [
+            &variant.color0,
+            &variant.color1,
+            &variant.color2,
+            &variant.color3,
+        ]
+        .map(|c| c.primary.hex())
+    }
+
+    #[test]
+    fn invalid_inputs_report_their_failure() {
+        let seed = Color::from_hex("#3A7BD5").unwrap();
+        let err = Primaries::from_seeds(&[], Harmony::Analogous).unwrap_err();
+        assert!(matches!(err, Error::NoSeeds));
+        let seeds = vec![seed.clone(); PRIMARY_COLORS + 1];
+        let err = Primaries::from_seeds(&seeds, Harmony::Analogous).unwrap_err();
+        assert!(matches!(err, Error::TooManySeeds { max: 4, found: 5 }));
+        let primaries = Primaries::from_colors("test", vec![seed]);
+        let err = Palette::from_primaries(&primaries, Gradiant::Standard).unwrap_err();
+        assert!(matches!(err, Error::NotEnoughColors { found: 1, .. }));
+        assert_eq!(err.exit_code(), 6);
+    }
+
+    #[test]
+    fn vary_sorts_substitutes_by_luminocity() {
+        let colors = ["#0A1430", "#1E3C8C", "#2A5AB0", "#3A7BD5"];
+        let colors: Vec<Color> = colors.map(|c| Color::from_hex(c).unwrap()).to_vec();
+        let primaries = Primaries::from_colors("test", colors.clone());
+        let variation = Variation {
+            min_delta_e: 20.0,
+            harmony: Harmony::Complementary,
+        };
+        let varied = primaries.vary(&variation);
+        assert_eq!(varied.colors.len(), PRIMARY_COLORS);
+        assert!(!varied.synthesized.is_empty());
+        assert!(varied.colors.windows(2).all(|w| w[0] <= w[1]));
+        for (n, color) in varied.colors.iter().enumerate() {
+            let original = colors.iter().any(|c| c.hex() == color.hex());
+            assert_eq!(original, !varied.synthesized.contains(&n), "color{n}");
+        }
+    }
+
+    #[test]
+    fn invalid_colors_are_rejected() {
+        for color in ["#GG0000", "rgb(1,2)", "rgb(1,2,300)", "blue"] {
+            let err = color.parse::<Color>().err();
+            assert!(matches!(err, Some(Error::InvalidColor(_))), "{color:?}");
+        }
+        assert_eq!("rgb(58,123,213)".parse::<Color>().unwrap().hex(), "#3A7BD5");
+    }
+
+    #[test]
+    fn color_mix_keeps_endpoints() {
+        let a = Color::from_hex("#3A7BD5").unwrap();
+        let b = Color::from_hex("#805010").unwrap();
+        assert_eq!(a.mix(&b, 0.0).hex(), a.hex());
+        assert_eq!(a.mix(&b, 1.0).hex(), b.hex());
+        let mid = a.mix(&b, 0.5);
+        assert_ne!(mid.hex(), a.hex());
+        assert_ne!(mid.hex(), b.hex());
+    }
+
+    #[test]
+    fn palette_mix_interpolates_material() {
+        let from = palette();
+        let colors = ["#200810", "#D53A7B", "#108050", "#F0E0F0"];
+        let colors = colors.map(|c| Color::from_hex(c).unwrap()).to_vec();
+        let primaries = Primaries::from_colors("other", colors);
+        let to = Palette::from_primaries(&primaries, Gradiant::Standard).unwrap();
+        let role = |p: &Palette| p.material.as_ref().unwrap().dark.primary.hex();
+        assert_eq!(role(&from.mix(&to, 0.0)), role(&from));
+        assert_eq!(role(&from.mix(&to, 1.0)), role(&to));
+        let mid = role(&from.mix(&to, 0.5));
+        assert_ne!(mid, role(&from));
+        assert_ne!(mid, role(&to));
+        assert_eq!(
+            from.mix(&to, 0.25).color1.primary,
+            from.color1.primary.mix(&to.color1.primary, 0.25)
+        );
+    }
+
+    #[test]
+    fn parse_slot_accepts_palette_keys() {
+        assert!(matches!(
+            parse_slot("color0.primary"),
+            Ok((0, Slot::Primary))
+        ));
+        assert!(matches!(parse_slot("color3.text"), Ok((3, Slot::Text))));
+        assert!(matches!(
+            parse_slot("color2.accents[8]"),
+            Ok((2, Slot::Accent(8)))
+        ));
+    }
+
+    #[test]
+    fn parse_slot_rejects_invalid_keys() {
+        for key in [
+            "color1",
+            "color4.primary",
+            "color1.background",
+            "color1.accents[x]",
+            "color1.accents[4",
+            "color1.accents[9]",
+        ] {
+            let err = parse_slot(key).err();
+            assert!(matches!(err, Some(Error::InvalidSlot { .. })), "{key:?}");
+        }
+    }
+
+    #[test]
+    fn get_reads_the_set_slot() {
+        let mut palette = palette();
+        let red = Color::from_hex("#CC0000").unwrap();
+        palette.set("color2.text", red.clone()).unwrap();
+        assert_eq!(palette.get("color2.text").unwrap(), &red);
+        assert_ne!(palette.get("color2.primary").unwrap(), &red);
+    }
+
+    #[test]
+    fn variants_reorder_the_same_colors() {
+        let palette = palette();
+        assert_eq!(palette.theme, Theme::Dark);
+        let dark = primary_hexes(palette.dark.as_ref().unwrap());
+        let mut light = primary_hexes(palette.light.as_ref().unwrap());
+        light.reverse();
+        assert_eq!(dark, light);
+        assert_eq!(dark, ["#101820", "#805010", "#3A7BD5", "#F0F0F0"]);
+    }
+
+    #[test]
+    fn set_theme_activates_variant() {
+        let mut palette = palette();
+        palette.set_theme(Theme::Light).unwrap();
+        assert_eq!(palette.theme, Theme::Light);
+        assert_eq!(palette.color0.primary.hex(), "#F0F0F0");
+        let light = palette.light.as_ref().unwrap();
+        assert_eq!(palette.color0.text, light.color0.text);
+        palette.set_theme(Theme::Dark).unwrap();
+        assert_eq!(palette.color0.primary.hex(), "#101820");
+    }
+
+    #[test]
+    fn fill_missing_restores_variants_from_active_colors() {
+        let mut palette = palette();
+        palette.set_theme(Theme::Light).unwrap();
+        let (dark, light) = (palette.dark.take(), palette.light.take());
+        palette.material = None;
+        palette.fill_missing().unwrap();
+        assert!(palette.material.is_some());
+        let primaries = |v: &Option<Variant>| primary_hexes(v.as_ref().unwrap());
+        assert_eq!(primaries(&palette.dark), primaries(&dark));
+        assert_eq!(primaries(&palette.light), primaries(&light));
+    }
+
+    #[test]
+    fn set_overrides_every_variant() {
+        let mut palette = palette();
+        let red = Color::from_hex("#CC0000").unwrap();
+        palette.set("color1.accents[4]", red.clone()).unwrap();
+        assert_eq!(palette.color1.accents[4], red);
+        assert_eq!(palette.dark.as_ref().unwrap().color1.accents[4], red);
+        assert_eq!(palette.light.as_ref().unwrap().color1.accents[4], red);
+    }
+
+    #[test]
+    fn serialize_keeps_both_variants() {
+        let palette = palette();
+        let content = toml::to_string(&palette).unwrap();
+        let table: toml::Table = toml::from_str(&content).unwrap();
+        assert!(table.contains_key("light"));
+        assert!(table.contains_key("dark"));
+        let json: serde_json::Value = serde_json::to_value(&palette).unwrap();
+        assert!(json["dark"].is_object() && json["light"].is_object());
+        let restored: Palette = toml::from_str(&content).unwrap();
+        let dark = |p: &Palette| primary_hexes(p.dark.as_ref().unwrap());
+        assert_eq!(dark(&restored), dark(&palette));
+    }
+}
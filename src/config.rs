@@ -1,55 +1,1139 @@
 //! File Based Configuration
 
-use std::{collections::HashMap, path::PathBuf};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::color::Gradiant;
+use crate::builtin;
+use crate::color::{Color, Gradiant, Palette, Primaries, Theme, ThemeMode};
+use crate::error::{self, Error};
+use crate::format::PaletteFormat;
+use crate::harmony::Variation;
+use crate::template::Engine;
 
-#[derive(Debug, Serialize, Deserialize)]
+fn default_true() -> bool {
+    true
+}
+
+/// Placeholder in glob targets replaced with the source relative path
+const RELPATH: &str = "{relpath}";
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct TemplateConfig {
-    /// Template Source
+    /// Template Source (relative to the config file or `builtin:<name>`)
+    #[serde(default)]
     pub template: String,
-    /// Template Render Destination
+    /// Directory or glob of template sources rendered individually
+    pub source: Option<String>,
+    /// Template Render Destination (relative to the config file)
+    ///
+    /// Sources expanded from a glob replace `{relpath}` with their path
+    /// relative to the glob base directory with the extension stripped.
+    /// Builtin templates default to their usual application config path.
+    #[serde(default)]
     pub target: String,
+    /// Enable or disable rendering of the template
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Gradiant override for the template
+    pub gradiant: Option<Gradiant>,
+    /// Only render the template for the specified theme (dark/light)
+    pub only_theme: Option<Theme>,
+    /// Only render the template when the condition is met
+    pub when: Option<Condition>,
+}
+
+/// Condition determining if a template should be rendered
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Condition {
+    /// Binary that must exist on `$PATH`
+    pub command: Option<String>,
+    /// Path that must exist (relative to the config file)
+    pub exists: Option<String>,
+}
+
+impl Condition {
+    /// Check if the binary exists as an executable file on `$PATH`
+    fn has_command(command: &str) -> bool {
+        let Some(paths) = std::env::var_os("PATH") else {
+            return false;
+        };
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(command))
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .any(|meta| meta.is_file() && is_executable(&meta))
+    }
+    /// Describe the first unmet condition
+    pub fn unmet(&self) -> Option<String> {
+        if let Some(command) = self.command.as_ref() {
+            if !Self::has_command(command) {
+                return Some(format!("command {command:?} not found"));
+            }
+        }
+        if let Some(path) = self.exists.as_ref() {
+            if !Path::new(path).exists() {
+                return Some(format!("path {path:?} does not exist"));
+            }
+        }
+        None
+    }
+}
+
+/// Check the file has any executable permission bit set
+#[cfg(unix)]
+fn is_executable(meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o111 != 0
+}
+
+/// Executable permissions are not tracked so any file on `$PATH` counts
+#[cfg(not(unix))]
+fn is_executable(_: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// Individual template render resolved from a template configuration
+#[derive(Debug)]
+pub struct TemplateRender {
+    pub name: String,
+    pub template: String,
+    pub target: String,
+}
+
+/// Unescape a glob path component, or `None` when it contains wildcards
+///
+/// Escapes are the single character classes written by [`glob::Pattern::escape`].
+fn glob_literal(component: &str) -> Option<String> {
+    let mut literal = String::new();
+    let mut chars = component.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                let mut class = chars.clone();
+                match (class.next(), class.next()) {
+                    (Some(escaped @ ('[' | ']' | '*' | '?')), Some(']')) => {
+                        literal.push(escaped);
+                        chars = class;
+                    }
+                    _ => return None,
+                }
+            }
+            '*' | '?' => return None,
+            c => literal.push(c),
+        }
+    }
+    Some(literal)
+}
+
+/// Split a glob pattern into its literal base directory and full pattern
+fn glob_base(source: &str) -> (PathBuf, String) {
+    let components: Vec<_> = Path::new(source).components().collect();
+    let literal: Vec<String> = components
+        .iter()
+        .map_while(|c| glob_literal(&c.as_os_str().to_string_lossy()))
+        .collect();
+    let base: PathBuf = literal.iter().collect();
+    if literal.len() == components.len() && base.is_dir() {
+        let pattern = Path::new(source).join("**").join("*");
+        return (base, pattern.to_string_lossy().into_owned());
+    }
+    (base, source.to_owned())
+}
+
+impl TemplateConfig {
+    /// Expand template configuration into individual renders
+    pub fn expand(&self, name: &str) -> Result<Vec<TemplateRender>> {
+        let Some(source) = self.source.as_ref() else {
+            if self.template.is_empty() {
+                return Err(anyhow!("template {name:?} missing template or source"));
+            }
+            let target = match builtin::from_path(&self.template) {
+                Some(builtin) if self.target.is_empty() => {
+                    let builtin = builtin.context(format!("template {name:?} invalid"))?;
+                    expand_path(builtin.target, Path::new(""))?
+                }
+                _ => self.target.clone(),
+            };
+            if target.is_empty() {
+                return Err(anyhow!("template {name:?} missing target"));
+            }
+            return Ok(vec![TemplateRender {
+                name: name.to_owned(),
+                template: self.template.clone(),
+                target,
+            }]);
+        };
+        if !self.target.contains(RELPATH) {
+            return Err(anyhow!("template {name:?} target missing {RELPATH}"));
+        }
+        let (base, pattern) = glob_base(source);
+        let mut paths: Vec<PathBuf> = glob::glob(&pattern)
+            .context(format!("template {name:?} has invalid source {source:?}"))?
+            .filter_map(|p| p.ok())
+            .filter(|p| p.is_file())
+            .collect();
+        paths.sort();
+        if paths.is_empty() {
+            log::warn!("template {name:?} source {source:?} matched no files");
+        }
+        paths
+            .into_iter()
+            .map(|path| {
+                let relpath = path
+                    .strip_prefix(&base)
+                    .context(format!("{path:?} outside of {base:?}"))?
+                    .with_extension("");
+                let relpath = relpath.to_string_lossy();
+                Ok(TemplateRender {
+                    name: format!("{name}:{relpath}"),
+                    template: path.to_string_lossy().into_owned(),
+                    target: self.target.replace(RELPATH, &relpath),
+                })
+            })
+            .collect()
+    }
+    /// Describe why the template is skipped for the given theme, if it is
+    pub fn skip_reason(&self, theme: Theme) -> Option<String> {
+        if !self.enabled {
+            return Some("template disabled".to_owned());
+        }
+        if let Some(only) = self.only_theme.filter(|only| *only != theme) {
+            return Some(format!("only rendered for {only} theme"));
+        }
+        self.when
+            .as_ref()
+            .and_then(|when| when.unmet())
+            .map(|unmet| format!("condition unmet: {unmet}"))
+    }
+}
+
+/// Palette files written in other applications' formats on every run
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Exports {
+    /// Pywal `colors.json` destination (e.g. `~/.cache/wal/colors.json`)
+    pub pywal: Option<String>,
+    /// GIMP/Inkscape `.gpl` palette destination
+    pub gpl: Option<String>,
+    /// Adobe Swatch Exchange `.ase` destination
+    pub ase: Option<String>,
+    /// Krita `.kpl` palette destination
+    pub kpl: Option<String>,
+}
+
+impl Exports {
+    /// List configured export destinations and their formats
+    pub fn targets(&self) -> Vec<(PaletteFormat, &String)> {
+        [
+            (PaletteFormat::Pywal, &self.pywal),
+            (PaletteFormat::Gpl, &self.gpl),
+            (PaletteFormat::Ase, &self.ase),
+            (PaletteFormat::Kpl, &self.kpl),
+        ]
+        .into_iter()
+        .filter_map(|(format, path)| path.as_ref().map(|p| (format, p)))
+        .collect()
+    }
+}
+
+/// Named subset of templates and settings for a machine or session
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct Profile {
+    /// Hostnames automatically selecting the profile
+    pub hostnames: Vec<String>,
+    /// Templates rendered by the profile (all templates when empty)
+    pub templates: Vec<String>,
+    /// Configuration overrides applied while the profile is active
+    #[schemars(with = "HashMap<String, serde_json::Value>")]
+    pub settings: toml::Table,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct Config {
+    /// Additional configuration files merged beneath this file (supports globs)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
     /// Default Gradiant for Palette generation
     pub gradiant: Gradiant,
+    /// Active color-mode (dark, light or auto to follow the image)
+    pub theme: ThemeMode,
+    /// Number of previous template renders to keep as backups
+    pub backups: usize,
     /// Template Configuration
     pub templates: HashMap<String, TemplateConfig>,
+    /// User-defined variables passed to every template
+    #[schemars(with = "HashMap<String, serde_json::Value>")]
+    pub vars: HashMap<String, toml::Value>,
+    /// Palette colors pinned regardless of image (e.g. `"color1.accents[4]"`)
+    pub overrides: HashMap<String, Color>,
+    /// Minimum variation between primary colors by gradiant (`auto` applies to all)
+    ///
+    /// Only palettes generated from primary colors are varied; palettes read
+    /// from a file or history are used as stored.
+    pub variation: HashMap<Gradiant, Variation>,
+    /// Built-in palette export targets
+    pub exports: Exports,
+    /// Active profile (defaults to the profile matching the hostname)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Named profiles selecting templates and overriding settings
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// Check the target directory (or its nearest existing parent) is writable
+///
+/// Symlinked targets are checked at their resolved location since that is
+/// where the render is written. Nothing is written during the check.
+fn check_writable(target: &Path) -> Result<()> {
+    let target = std::fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf());
+    let mut dir = target.parent().unwrap_or(Path::new("."));
+    while !dir.exists() {
+        dir = dir.parent().unwrap_or(Path::new("."));
+    }
+    if !dir.is_dir() {
+        return Err(anyhow!("{dir:?} is not a directory"));
+    }
+    if !is_writable(dir) {
+        return Err(anyhow!("{dir:?} is not writable"));
+    }
+    Ok(())
+}
+
+/// Check the current user may create files within the directory
+#[cfg(unix)]
+fn is_writable(dir: &Path) -> bool {
+    use rustix::fs::Access;
+    rustix::fs::access(dir, Access::WRITE_OK | Access::EXEC_OK).is_ok()
+}
+
+/// Check the directory is not marked read-only
+#[cfg(not(unix))]
+fn is_writable(dir: &Path) -> bool {
+    std::fs::metadata(dir).is_ok_and(|meta| !meta.permissions().readonly())
+}
+
+/// Environment variable used to override the user configuration path
+const CONFIG_ENV: &str = "WALLBASH_CONFIG";
+/// Files checked for the current machine hostname
+const HOSTNAME_FILES: [&str; 2] = ["/proc/sys/kernel/hostname", "/etc/hostname"];
+/// Prefix of environment variables overriding configuration keys
+const ENV_PREFIX: &str = "WALLBASH_";
+/// Environment variables with the prefix that are not configuration keys
+const ENV_RESERVED: [&str; 1] = [CONFIG_ENV];
+
+/// Configuration table and where it was loaded from
+#[derive(Debug)]
+pub struct Layer {
+    pub source: String,
+    pub table: toml::Table,
+}
+
+/// Read hostname of the current machine
+fn hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .ok()
+        .into_iter()
+        .chain(
+            HOSTNAME_FILES
+                .iter()
+                .filter_map(|f| std::fs::read_to_string(f).ok()),
+        )
+        .map(|h| h.trim().to_owned())
+        .find(|h| !h.is_empty())
+}
+
+/// Find the profile listing a hostname (an error when several do)
+fn host_profile<'a>(profiles: &'a HashMap<String, Profile>, host: &str) -> Result<Option<&'a str>> {
+    let mut names: Vec<&str> = profiles
+        .iter()
+        .filter(|(_, p)| p.hostnames.iter().any(|h| h == host))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    names.sort();
+    match names[..] {
+        [] => Ok(None),
+        [name] => Ok(Some(name)),
+        _ => Err(Error::AmbiguousProfile {
+            host: host.to_owned(),
+            profiles: names.into_iter().map(str::to_owned).collect(),
+        }
+        .into()),
+    }
+}
+
+/// Build configuration layer for the active profile
+///
+/// The profile is selected by the `profile` key or by matching hostname.
+fn profile_layer(layers: &[Layer]) -> Result<Option<Layer>> {
+    let config = Config::from_layers(layers)?;
+    let name = match config.profile {
+        Some(name) => name,
+        None => {
+            let Some(host) = hostname() else {
+                return Ok(None);
+            };
+            match host_profile(&config.profiles, &host)? {
+                Some(name) => name.to_owned(),
+                None => return Ok(None),
+            }
+        }
+    };
+    let profile = config
+        .profiles
+        .get(&name)
+        .ok_or_else(|| Error::NoSuchProfile(name.clone()))?;
+    log::info!("using profile {name:?}");
+    let mut table = profile.settings.clone();
+    if !profile.templates.is_empty() {
+        for template in config.templates.keys() {
+            if !profile.templates.contains(template) {
+                let key = toml::Value::from(template.as_str());
+                merge(
+                    &mut table,
+                    parse_assignment(&format!("templates.{key}.enabled"), "false")?,
+                );
+            }
+        }
+    }
+    Ok(Some(Layer {
+        source: format!("profile {name:?}"),
+        table,
+    }))
+}
+
+/// Parse a `key = value` assignment into a table (bare values become strings)
+fn parse_assignment(key: &str, value: &str) -> Result<toml::Table> {
+    toml::from_str(&format!("{key} = {value}"))
+        .or_else(|_| toml::from_str(&format!("{key} = {}", toml::Value::from(value))))
+        .map_err(|source| {
+            Error::InvalidOverride {
+                key: key.to_owned(),
+                source,
+            }
+            .into()
+        })
+}
+
+/// Collect `WALLBASH_*` environment overrides as dotted keys
+///
+/// Nested keys are separated by a double underscore
+/// (e.g. `WALLBASH_TEMPLATES__KITTY__ENABLED`). Each key is matched
+/// case-insensitively against keys already present in `base`, so table
+/// entries such as `templates.Kitty` keep their case; unknown keys are
+/// lowercased.
+fn env_overrides(
+    vars: impl IntoIterator<Item = (String, String)>,
+    base: &toml::Table,
+) -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = vars
+        .into_iter()
+        .filter(|(key, _)| key.starts_with(ENV_PREFIX) && !ENV_RESERVED.contains(&key.as_str()))
+        .map(|(key, value)| {
+            let mut table = Some(base);
+            let key = key[ENV_PREFIX.len()..]
+                .split("__")
+                .map(|k| {
+                    let lower = k.to_lowercase();
+                    let found = table.and_then(|t| {
+                        t.get_key_value(&lower)
+                            .or_else(|| t.iter().find(|(key, _)| key.to_lowercase() == lower))
+                    });
+                    table = found.and_then(|(_, value)| value.as_table());
+                    let key = found.map_or(lower, |(key, _)| key.to_owned());
+                    toml::Value::from(key).to_string()
+                })
+                .collect::<Vec<_>>()
+                .join(".");
+            (key, value)
+        })
+        .collect();
+    vars.sort();
+    vars
+}
+
+/// Flatten nested tables into dotted keys and their leaf values
+fn flatten(prefix: &str, table: &toml::Table) -> Vec<(String, toml::Value)> {
+    let mut values = vec![];
+    for (key, value) in table {
+        let bare = key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        let key = match bare {
+            true => key.to_owned(),
+            false => toml::Value::from(key.as_str()).to_string(),
+        };
+        let key = match prefix.is_empty() {
+            true => key.to_owned(),
+            false => format!("{prefix}.{key}"),
+        };
+        match value {
+            toml::Value::Table(table) => values.extend(flatten(&key, table)),
+            value => values.push((key, value.clone())),
+        }
+    }
+    values
+}
+/// Record an error as a problem when collecting problems, otherwise return it
+fn collect<T>(result: Result<T>, problems: &mut Option<&mut Vec<String>>) -> Result<Option<T>> {
+    match (result, problems) {
+        (Ok(value), _) => Ok(Some(value)),
+        (Err(err), Some(problems)) => {
+            problems.push(format!("{err:#}"));
+            Ok(None)
+        }
+        (Err(err), None) => Err(err),
+    }
+}
+
+/// Categorize an error as a configuration error unless already categorized
+///
+/// Failures raised as a typed [`Error`] (e.g. parse errors or include cycles)
+/// keep their variant so callers can match on them. Remaining failures come
+/// from resolving paths and conditions of configuration values.
+fn config_error(err: anyhow::Error) -> Error {
+    match err.downcast::<Error>() {
+        Ok(err) => err,
+        Err(err) => Error::InvalidValue(err.into()),
+    }
+}
+
+/// Configuration directory name within XDG config directories
+const CONFIG_DIR: &str = "wallbash";
+/// Configuration filename within configuration directories
+const CONFIG_FILE: &str = "config.toml";
+/// Drop-in directory merged after a configuration file
+const DROPIN_DIR: &str = "config.d";
+/// Maximum depth of nested configuration includes
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Recursively merge configuration layer into base (layer takes precedence)
+fn merge(base: &mut toml::Table, layer: toml::Table) {
+    for (key, value) in layer {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(layer)) => merge(base, layer),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Expand `~` and environment variables and resolve path relative to base
+pub fn expand_path(path: &str, base: &Path) -> Result<String> {
+    let expanded = shellexpand::full(path).context(format!("failed to expand path {path:?}"))?;
+    Ok(base.join(expanded.as_ref()).to_string_lossy().into_owned())
+}
+
+/// Resolve template paths of a configuration layer relative to its directory
+fn resolve_paths(table: &mut toml::Table, base: &Path) -> Result<()> {
+    if let Some(toml::Value::Table(profiles)) = table.get_mut("profiles") {
+        for (name, profile) in profiles.iter_mut() {
+            if let Some(toml::Value::Table(settings)) = profile.get_mut("settings") {
+                resolve_paths(settings, base).context(format!("invalid profile {name:?}"))?;
+            }
+        }
+    }
+    if let Some(toml::Value::Table(exports)) = table.get_mut("exports") {
+        for (name, path) in exports.iter_mut() {
+            if let toml::Value::String(path) = path {
+                *path = expand_path(path, base).context(format!("export {name:?} invalid"))?;
+            }
+        }
+    }
+    let Some(toml::Value::Table(templates)) = table.get_mut("templates") else {
+        return Ok(());
+    };
+    for (name, template) in templates.iter_mut() {
+        let toml::Value::Table(template) = template else {
+            continue;
+        };
+        for key in ["template", "source", "target"] {
+            if let Some(toml::Value::String(path)) = template.get_mut(key) {
+                if key == "template" && path.starts_with(builtin::PREFIX) {
+                    continue;
+                }
+                // sources are globs so the base directory must match literally
+                let base = match key {
+                    "source" => PathBuf::from(glob::Pattern::escape(&base.to_string_lossy())),
+                    _ => base.to_path_buf(),
+                };
+                *path = expand_path(path, &base)
+                    .context(format!("template {name:?} has invalid {key:?}"))?;
+            }
+        }
+        if let Some(toml::Value::Table(when)) = template.get_mut("when") {
+            if let Some(toml::Value::String(path)) = when.get_mut("exists") {
+                *path = expand_path(path, base)
+                    .context(format!("template {name:?} has invalid \"when.exists\""))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Find configuration files matching a glob pattern relative to base
+///
+/// The base directory is matched literally even when it contains glob
+/// metacharacters.
+fn glob_paths(pattern: &str, base: &Path) -> Result<Vec<PathBuf>> {
+    let base = glob::Pattern::escape(&base.to_string_lossy());
+    let pattern = expand_path(pattern, Path::new(&base))?;
+    let mut paths: Vec<PathBuf> = glob::glob(&pattern)
+        .map_err(|source| Error::IncludePattern {
+            pattern: pattern.clone(),
+            source,
+        })?
+        .filter_map(|p| p.ok())
+        .filter(|p| p.is_file())
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Read a single configuration file and its includes as a raw toml table
+///
+/// Included files are merged first so the including file takes precedence.
+/// `parents` lists the canonical paths of the files including this one.
+fn read_layer(path: &Path, parents: &mut Vec<PathBuf>) -> Result<toml::Table> {
+    if parents.len() > MAX_INCLUDE_DEPTH {
+        return Err(Error::IncludeDepth(path.to_owned()).into());
+    }
+    let read_err = |source| Error::ConfigRead {
+        path: path.to_owned(),
+        source,
+    };
+    let canonical = std::fs::canonicalize(path).map_err(read_err)?;
+    if let Some(start) = parents.iter().position(|p| p == &canonical) {
+        let cycle = parents[start..].iter().chain([&canonical]).cloned();
+        return Err(Error::IncludeCycle(cycle.collect()).into());
+    }
+    log::debug!("reading config layer {path:?}");
+    let cfg = std::fs::read_to_string(path).map_err(read_err)?;
+    let mut table: toml::Table = toml::from_str(&cfg).map_err(|source| Error::ConfigParse {
+        path: path.to_owned(),
+        source,
+    })?;
+    let base = std::path::absolute(path)
+        .ok()
+        .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+        .unwrap_or_default();
+    resolve_paths(&mut table, &base).context(format!("invalid config {path:?}"))?;
+    // merge included configuration beneath the current file
+    let includes: Vec<String> = match table.remove("include") {
+        Some(include) => include.try_into().map_err(|source| Error::IncludeList {
+            path: path.to_owned(),
+            source,
+        })?,
+        None => vec![],
+    };
+    let mut merged = toml::Table::new();
+    parents.push(canonical);
+    for pattern in includes {
+        let paths = glob_paths(&pattern, &base)?;
+        if paths.is_empty() {
+            log::warn!("config include {pattern:?} in {path:?} matched no files");
+        }
+        for include in paths {
+            merge(&mut merged, read_layer(&include, parents)?);
+        }
+    }
+    parents.pop();
+    merge(&mut merged, table);
+    Ok(merged)
+}
+
+/// Read a top-level configuration file followed by its drop-in directory
+fn read_config(path: &Path) -> Result<toml::Table> {
+    let mut table = read_layer(path, &mut vec![])?;
+    if let Some(dropin) = path.parent().map(|p| p.join(DROPIN_DIR)) {
+        for path in glob_paths("*.toml", &dropin)? {
+            log::info!("loading config drop-in {path:?}");
+            merge(&mut table, read_layer(&path, &mut vec![])?);
+        }
+    }
+    Ok(table)
+}
+
+/// List system-wide configuration files (lowest precedence first)
+fn system_paths() -> Vec<PathBuf> {
+    let dirs = std::env::var("XDG_CONFIG_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/etc/xdg".to_owned());
+    dirs.split(':')
+        .filter(|dir| !dir.is_empty())
+        .rev()
+        .map(|dir| PathBuf::from(dir).join(CONFIG_DIR).join(CONFIG_FILE))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Determine user configuration file from arguments, environment or defaults
+fn user_path(path: Option<&String>) -> Result<Option<PathBuf>> {
+    let explicit = path
+        .cloned()
+        .or_else(|| std::env::var(CONFIG_ENV).ok().filter(|p| !p.is_empty()));
+    if let Some(path) = explicit {
+        let path = PathBuf::from(shellexpand::tilde(&path).as_ref());
+        if !path.exists() {
+            return Err(Error::ConfigNotFound(path).into());
+        }
+        return Ok(Some(path));
+    }
+    let config_dir = dirs::config_dir().ok_or(Error::NoConfigDir)?;
+    let path = config_dir.join(CONFIG_DIR).join(CONFIG_FILE);
+    if path.is_file() {
+        return Ok(Some(path));
+    }
+    let legacy = config_dir.join(CONFIG_FILE);
+    if legacy.is_file() {
+        log::warn!("using legacy config path {legacy:?}. please move it to {path:?}");
+        return Ok(Some(legacy));
+    }
+    Ok(None)
 }
 
 impl Config {
     /// Read Configuration from Path or Default
-    pub fn new(path: Option<&String>) -> Result<Self> {
-        // get filepath for configuration
-        let path = match path {
-            Some(path) => {
-                let path = PathBuf::from(path);
-                if !path.exists() {
-                    return Err(anyhow!("no such config file: {path:?}"));
-                }
-                path
+    ///
+    /// See [`Config::layers`] for the order configuration is merged in.
+    pub fn new(path: Option<&String>, sets: &[String]) -> error::Result<Self> {
+        Self::from_layers(&Self::layers(path, sets)?)
+    }
+
+    /// Collect configuration layers (lowest precedence first)
+    ///
+    /// System configuration found in `$XDG_CONFIG_DIRS` is merged first and
+    /// then overridden by the user configuration, which is selected from the
+    /// specified path, `$WALLBASH_CONFIG` or `~/.config/wallbash/config.toml`.
+    /// Each file is merged above its `include` list and beneath its
+    /// `config.d/*.toml` drop-ins. Settings of the active profile are applied
+    /// next, followed by `WALLBASH_*` environment variables and `--set`
+    /// overrides.
+    pub fn layers(path: Option<&String>, sets: &[String]) -> error::Result<Vec<Layer>> {
+        Self::read_layers(path, sets, &mut None).map_err(config_error)
+    }
+
+    /// Load configuration collecting problems instead of failing on the first
+    ///
+    /// Layers that fail to parse are reported and skipped so the remaining
+    /// configuration can still be validated with [`Config::check`].
+    pub fn load_checked(path: Option<&String>, sets: &[String]) -> (Self, Vec<String>) {
+        let mut problems = vec![];
+        let layers = match Self::read_layers(path, sets, &mut Some(&mut problems)) {
+            Ok(layers) => layers,
+            Err(err) => {
+                problems.push(format!("{err:#}"));
+                vec![]
             }
-            None => dirs::config_dir()
-                .expect("failed to find config directory")
-                .join("config.toml"),
         };
-        // read configuration file or use default
-        Ok(match path.exists() {
-            true => {
-                let cfg = std::fs::read_to_string(path).context("failed to read config file")?;
-                toml::from_str(&cfg).context("failed to parse config")?
+        let config = match Self::from_layers(&layers) {
+            Ok(config) => config,
+            Err(err) => {
+                problems.push(format!("{err:#}"));
+                Self::default()
             }
-            false => {
-                log::warn!("config file missing. using default values");
-                Config::default()
+        };
+        problems.extend(config.check());
+        (config, problems)
+    }
+
+    /// Read configuration layers, recording layer errors in `problems` when given
+    fn read_layers(
+        path: Option<&String>,
+        sets: &[String],
+        problems: &mut Option<&mut Vec<String>>,
+    ) -> Result<Vec<Layer>> {
+        let user = user_path(path)?;
+        if user.is_none() {
+            log::warn!("user config file missing. using default values");
+        }
+        let mut layers = vec![];
+        for path in system_paths().into_iter().chain(user) {
+            log::info!("loading config {path:?}");
+            if let Some(table) = collect(read_config(&path), problems)? {
+                layers.push(Layer {
+                    source: path.to_string_lossy().into_owned(),
+                    table,
+                });
             }
-        })
+        }
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let mut files = toml::Table::new();
+        for layer in &layers {
+            merge(&mut files, layer.table.clone());
+        }
+        let mut env = toml::Table::new();
+        for (key, value) in env_overrides(std::env::vars(), &files) {
+            if let Some(table) = collect(parse_assignment(&key, &value), problems)? {
+                merge(&mut env, table);
+            }
+        }
+        let resolved = resolve_paths(&mut env, &cwd).context("invalid environment override");
+        collect(resolved, problems)?;
+        layers.push(Layer {
+            source: "env".to_owned(),
+            table: env,
+        });
+        let mut cli = toml::Table::new();
+        for set in sets {
+            let assignment = set
+                .split_once('=')
+                .ok_or_else(|| Error::MalformedOverride(set.clone()).into())
+                .and_then(|(key, value)| parse_assignment(key.trim(), value.trim()));
+            if let Some(table) = collect(assignment, problems)? {
+                merge(&mut cli, table);
+            }
+        }
+        collect(
+            resolve_paths(&mut cli, &cwd).context("invalid --set override"),
+            problems,
+        )?;
+        layers.push(Layer {
+            source: "--set".to_owned(),
+            table: cli,
+        });
+        // profile settings sit between configuration files and overrides
+        if let Some(Some(profile)) = collect(profile_layer(&layers), problems)? {
+            layers.insert(layers.len() - 2, profile);
+        }
+        Ok(layers)
+    }
+
+    /// Merge configuration layers into the final configuration
+    pub fn from_layers(layers: &[Layer]) -> error::Result<Self> {
+        let mut table = toml::Table::new();
+        for layer in layers {
+            merge(&mut table, layer.table.clone());
+        }
+        table.try_into().map_err(Error::InvalidConfig)
+    }
+
+    /// List every effective configuration value and the layer it came from
+    pub fn origins(&self, layers: &[Layer]) -> Result<Vec<(String, toml::Value, String)>> {
+        let mut sources = HashMap::new();
+        for layer in layers {
+            for (key, _) in flatten("", &layer.table) {
+                sources.insert(key, layer.source.clone());
+            }
+        }
+        let effective = toml::Table::try_from(self).context("failed to serialize config")?;
+        Ok(flatten("", &effective)
+            .into_iter()
+            .map(|(key, value)| {
+                let source = sources.get(&key).cloned();
+                (key, value, source.unwrap_or_else(|| "default".to_owned()))
+            })
+            .collect())
+    }
+
+    /// Validate templates and overrides, collecting every problem found
+    pub fn check(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let mut names: Vec<&String> = self.templates.keys().collect();
+        names.sort();
+        for name in names {
+            let cfg = &self.templates[name];
+            if !cfg.enabled {
+                continue;
+            }
+            let renders = match cfg.expand(name) {
+                Ok(renders) => renders,
+                Err(err) => {
+                    problems.push(format!("{err:#}"));
+                    continue;
+                }
+            };
+            for render in renders {
+                let name = &render.name;
+                match builtin::read_template(&render.template) {
+                    Ok(base) => {
+                        if let Err(err) = Engine::new().check(&base) {
+                            problems.push(format!("template {name:?} {err:#}"));
+                        }
+                    }
+                    Err(err) => problems.push(format!(
+                        "template {name:?} cannot read {:?}: {err:#}",
+                        render.template
+                    )),
+                }
+                if let Err(err) = check_writable(Path::new(&render.target)) {
+                    problems.push(format!("template {name:?} target {err:#}"));
+                }
+            }
+        }
+        // validate override keys against a placeholder palette
+        let primaries = Primaries::from_colors("", vec![Color::from_rgb(0, 0, 0); 4]);
+        let mut palette = match Palette::from_primaries(&primaries, Gradiant::Standard) {
+            Ok(palette) => palette,
+            Err(err) => {
+                problems.push(format!("{err:#}"));
+                return problems;
+            }
+        };
+        for (key, color) in self.overrides.iter() {
+            if let Err(err) = palette.set(key, color.clone()) {
+                problems.push(format!("override {key:?} {err:#}"));
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a file within the directory, creating parent directories
+    fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn template(content: &str) -> TemplateConfig {
+        let content = format!("template = \"builtin:kitty\"\n{content}");
+        toml::from_str(&content).unwrap()
+    }
+
+    #[test]
+    fn skip_reason_reports_cause() {
+        assert_eq!(template("").skip_reason(Theme::Dark), None);
+        let disabled = template("enabled = false");
+        assert_eq!(
+            disabled.skip_reason(Theme::Dark).as_deref(),
+            Some("template disabled")
+        );
+        let light = template("only_theme = \"light\"");
+        assert_eq!(light.skip_reason(Theme::Light), None);
+        assert_eq!(
+            light.skip_reason(Theme::Dark).as_deref(),
+            Some("only rendered for light theme")
+        );
+        let missing = template("when = { command = \"wallbash-missing-command\" }");
+        let reason = missing.skip_reason(Theme::Dark).unwrap();
+        assert!(reason.starts_with("condition unmet"), "{reason}");
+    }
+
+    #[test]
+    fn only_theme_rejects_unknown_theme() {
+        let content = "template = \"builtin:kitty\"\nonly_theme = \"Dark\"";
+        assert!(toml::from_str::<TemplateConfig>(content).is_err());
+    }
+
+    #[test]
+    fn check_writable_does_not_write() {
+        let dir = tempfile::tempdir().unwrap();
+        check_writable(&dir.path().join("missing/kitty.conf")).unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+        let file = write(dir.path(), "file", "");
+        assert!(check_writable(&file.join("kitty.conf")).is_err());
+    }
+
+    #[test]
+    fn load_checked_collects_every_layer_problem() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(dir.path(), "config.toml", "gradiant = ");
+        let path = path.to_string_lossy().into_owned();
+        let sets = [
+            "backups".to_owned(),
+            "templates.kitty.template = 1".to_owned(),
+        ];
+        let (_, problems) = Config::load_checked(Some(&path), &sets);
+        assert!(
+            problems[0].contains("failed to parse config"),
+            "{problems:?}"
+        );
+        assert!(problems[1].contains("invalid override"), "{problems:?}");
+        assert!(problems.len() >= 3, "{problems:?}");
+    }
+
+    #[test]
+    fn env_overrides_keep_table_key_case() {
+        let base: toml::Table = toml::from_str("[templates.Kitty]\ntemplate = \"x\"").unwrap();
+        let vars = [
+            ("WALLBASH_TEMPLATES__KITTY__ENABLED", "false"),
+            ("WALLBASH_VARS__FONT_SIZE", "12"),
+            ("WALLBASH_CONFIG", "ignored.toml"),
+            ("HOME", "/root"),
+        ]
+        .map(|(k, v)| (k.to_owned(), v.to_owned()));
+        let overrides = env_overrides(vars, &base);
+        assert_eq!(
+            overrides,
+            [
+                (
+                    r#""templates"."Kitty"."enabled""#.to_owned(),
+                    "false".to_owned()
+                ),
+                (r#""vars"."font_size""#.to_owned(), "12".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_overrides_config_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "backups = 2\n[vars]\nfont = \"mono\"\nsize = 10";
+        let path = write(dir.path(), "config.toml", content);
+        let path = path.to_string_lossy().into_owned();
+        let sets = ["backups = 5".to_owned(), "vars.font = sans".to_owned()];
+        let layers = Config::layers(Some(&path), &sets).unwrap();
+        let config = Config::from_layers(&layers).unwrap();
+        assert_eq!(config.backups, 5);
+        assert_eq!(config.vars["font"], toml::Value::from("sans"));
+        assert_eq!(config.vars["size"], toml::Value::from(10));
+    }
+
+    #[test]
+    fn host_profile_requires_a_single_match() {
+        let config: Config = toml::from_str(
+            r#"
+            [profiles.laptop]
+            hostnames = ["thinkpad", "shared"]
+            [profiles.desktop]
+            hostnames = ["tower", "shared"]
+            "#,
+        )
+        .unwrap();
+        let profiles = &config.profiles;
+        assert_eq!(host_profile(profiles, "tower").unwrap(), Some("desktop"));
+        assert_eq!(host_profile(profiles, "server").unwrap(), None);
+        let err = config_error(host_profile(profiles, "shared").unwrap_err());
+        assert!(err.to_string().contains("desktop, laptop"), "{err}");
+        assert!(matches!(err, Error::AmbiguousProfile { .. }));
+    }
+
+    #[test]
+    fn profile_layer_sits_between_files_and_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = r#"
+            profile = "work"
+            backups = 1
+            [templates.kitty]
+            template = "builtin:kitty"
+            [templates.waybar]
+            template = "builtin:waybar"
+            [profiles.work]
+            templates = ["kitty"]
+            settings = { backups = 2, gradiant = "vibrant" }
+        "#;
+        let path = write(dir.path(), "config.toml", content);
+        let path = path.to_string_lossy().into_owned();
+        let layers = Config::layers(Some(&path), &["backups = 3".to_owned()]).unwrap();
+        let config = Config::from_layers(&layers).unwrap();
+        assert_eq!(config.backups, 3);
+        assert_eq!(config.gradiant, Gradiant::Vibrant);
+        assert!(config.templates["kitty"].enabled);
+        assert!(!config.templates["waybar"].enabled);
+    }
+
+    #[test]
+    fn includes_merge_beneath_file_and_dropins_above() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "base.toml", "backups = 1\ngradiant = \"mono\"");
+        write(
+            dir.path(),
+            "config.d/10-vibrant.toml",
+            "gradiant = \"vibrant\"",
+        );
+        let path = write(
+            dir.path(),
+            "config.toml",
+            "include = [\"base.toml\"]\ngradiant = \"pastel\"",
+        );
+        let table = read_config(&path).unwrap();
+        assert_eq!(table["backups"].as_integer(), Some(1));
+        assert_eq!(table["gradiant"].as_str(), Some("vibrant"));
+    }
+
+    #[test]
+    fn includes_match_base_with_glob_characters() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("[wallbash]");
+        write(&base, "conf/kitty.toml", "backups = 3");
+        let path = write(&base, "config.toml", "include = [\"conf/*.toml\"]");
+        let table = read_config(&path).unwrap();
+        assert_eq!(table["backups"].as_integer(), Some(3));
+    }
+
+    #[test]
+    fn template_sources_match_base_with_glob_characters() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("[x]");
+        write(&base, "tpl/kitty/theme.conf.j2", "");
+        write(&base, "tpl/waybar.css.j2", "");
+        let content = "[templates.all]\nsource = \"tpl/**/*.j2\"\ntarget = \"/out/{relpath}\"\n\
+                       [templates.dir]\nsource = \"tpl\"\ntarget = \"/out/{relpath}\"";
+        let path = write(&base, "config.toml", content);
+        let layers = Config::layers(Some(&path.to_string_lossy().into_owned()), &[]).unwrap();
+        let config = Config::from_layers(&layers).unwrap();
+        for name in ["all", "dir"] {
+            let renders = config.templates[name].expand(name).unwrap();
+            let targets: Vec<_> = renders.iter().map(|r| r.target.as_str()).collect();
+            assert_eq!(
+                targets,
+                ["/out/kitty/theme.conf", "/out/waybar.css"],
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn glob_literal_unescapes_components() {
+        assert_eq!(glob_literal("[[]x[]]").as_deref(), Some("[x]"));
+        assert_eq!(glob_literal("plain").as_deref(), Some("plain"));
+        assert_eq!(glob_literal("*.j2"), None);
+        assert_eq!(glob_literal("[ab]"), None);
+    }
+
+    #[test]
+    fn include_cycle_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.toml", "include = [\"b.toml\"]");
+        write(dir.path(), "b.toml", "include = [\"a.toml\"]");
+        let err = config_error(read_config(&dir.path().join("a.toml")).unwrap_err());
+        let Error::IncludeCycle(cycle) = &err else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(cycle.len(), 3);
+        assert_eq!(cycle.first(), cycle.last());
+    }
+
+    #[test]
+    fn config_failures_have_their_own_variant() {
+        let dir = tempfile::tempdir().unwrap();
+        let load = |content: &str| {
+            let path = write(dir.path(), "config.toml", content);
+            Config::layers(Some(&path.to_string_lossy().into_owned()), &[]).unwrap_err()
+        };
+        let err = load("include = 1");
+        assert!(matches!(err, Error::IncludeList { .. }), "{err:?}");
+        let err = load("include = [\"[\"]");
+        assert!(matches!(err, Error::IncludePattern { .. }), "{err:?}");
+        let err = load("[exports]\npywal = \"$WALLBASH_TEST_UNSET/colors.json\"");
+        assert!(matches!(err, Error::InvalidValue(_)), "{err:?}");
+        assert_eq!(err.exit_code(), 7);
+    }
+
+    #[test]
+    fn repeated_include_is_not_a_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "common.toml", "backups = 2");
+        write(dir.path(), "a.toml", "include = [\"common.toml\"]");
+        let path = write(
+            dir.path(),
+            "config.toml",
+            "include = [\"common.toml\", \"a.toml\"]",
+        );
+        let table = read_config(&path).unwrap();
+        assert_eq!(table["backups"].as_integer(), Some(2));
     }
 }
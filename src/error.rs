@@ -0,0 +1,149 @@
+//! Library Error Types
+
+use std::path::PathBuf;
+
+use crate::color::Theme;
+use crate::format::PaletteFormat;
+
+/// Errors raised while generating palettes and loading configuration
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read image {path:?}")]
+    ImageRead {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to decode image {path:?}")]
+    ImageDecode {
+        path: PathBuf,
+        #[source]
+        source: image::ImageError,
+    },
+    #[error("image has {pixels} pixels, at least {required} required")]
+    EmptyImage { pixels: usize, required: usize },
+    #[error("color clustering failed: no kmeans result available")]
+    NoKmeansResult,
+    #[error("color clustering failed: expected {expected} clusters, found {found}")]
+    ClusterCount { expected: usize, found: usize },
+    #[error("color clustering failed: cluster color is not a number")]
+    InvalidCluster,
+    #[error("invalid palette: no seed colors given")]
+    NoSeeds,
+    #[error("invalid palette: at most {max} seed colors allowed, found {found}")]
+    TooManySeeds { max: usize, found: usize },
+    #[error("invalid palette: expected {expected} primary colors, found {found}")]
+    NotEnoughColors { expected: usize, found: usize },
+    #[error("invalid palette: palette has no {0} variant")]
+    MissingVariant(Theme),
+    #[error("invalid color: {0:?}")]
+    InvalidColor(String),
+    #[error("invalid palette key {key:?}: {reason}")]
+    InvalidSlot { key: String, reason: &'static str },
+    #[error("invalid {format} palette")]
+    PaletteParse {
+        format: PaletteFormat,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("failed to serialize {format} palette")]
+    PaletteSerialize {
+        format: PaletteFormat,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("template error")]
+    Template(#[source] minijinja::Error),
+    #[error("failed to find config directory")]
+    NoConfigDir,
+    #[error("no such config file: {0:?}")]
+    ConfigNotFound(PathBuf),
+    #[error("failed to read config {path:?}")]
+    ConfigRead {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("failed to parse config {path:?}")]
+    ConfigParse {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("config include cycle: {}", .0.iter().map(|p| p.to_string_lossy()).collect::<Vec<_>>().join(" -> "))]
+    IncludeCycle(Vec<PathBuf>),
+    #[error("config includes nested too deeply at {0:?}")]
+    IncludeDepth(PathBuf),
+    #[error("invalid include list in {path:?}")]
+    IncludeList {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("invalid include pattern {pattern:?}")]
+    IncludePattern {
+        pattern: String,
+        #[source]
+        source: glob::PatternError,
+    },
+    #[error("invalid override {0:?} (expected key=value)")]
+    MalformedOverride(String),
+    #[error("invalid config override {key:?}")]
+    InvalidOverride {
+        key: String,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("no such profile: {0:?}")]
+    NoSuchProfile(String),
+    #[error("hostname {host:?} matches several profiles: {} (select one with --profile)", .profiles.join(", "))]
+    AmbiguousProfile { host: String, profiles: Vec<String> },
+    #[error("invalid configuration")]
+    InvalidConfig(#[source] toml::de::Error),
+    #[error("no templates in configuration")]
+    NoTemplates,
+    #[error("{0} config problems found")]
+    ConfigProblems(usize),
+    #[error("invalid configuration value")]
+    InvalidValue(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Error {
+    /// Process exit code for the error category
+    ///
+    /// Code `1` is left for uncategorized errors and `2` for usage errors.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::ImageRead { .. } | Self::ImageDecode { .. } => 3,
+            Self::EmptyImage { .. } => 4,
+            Self::NoKmeansResult | Self::ClusterCount { .. } | Self::InvalidCluster => 5,
+            Self::NoSeeds
+            | Self::TooManySeeds { .. }
+            | Self::NotEnoughColors { .. }
+            | Self::MissingVariant(_)
+            | Self::InvalidColor(_)
+            | Self::InvalidSlot { .. }
+            | Self::PaletteParse { .. }
+            | Self::PaletteSerialize { .. } => 6,
+            Self::Template(_) => 1,
+            Self::NoConfigDir
+            | Self::ConfigNotFound(_)
+            | Self::ConfigRead { .. }
+            | Self::ConfigParse { .. }
+            | Self::IncludeCycle(_)
+            | Self::IncludeDepth(_)
+            | Self::IncludeList { .. }
+            | Self::IncludePattern { .. }
+            | Self::MalformedOverride(_)
+            | Self::InvalidOverride { .. }
+            | Self::NoSuchProfile(_)
+            | Self::AmbiguousProfile { .. }
+            | Self::InvalidConfig(_)
+            | Self::NoTemplates
+            | Self::ConfigProblems(_)
+            | Self::InvalidValue(_) => 7,
+        }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
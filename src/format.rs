@@ -0,0 +1,217 @@
+//! Palette File Formats
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use anyhow::Context;
+
+use crate::color::{Gradiant, Palette};
+use crate::error::{Error, Result};
+use crate::pywal::PywalColors;
+use crate::swatch;
+
+/// Supported Palette Serialization Formats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaletteFormat {
+    Toml,
+    Json,
+    Yaml,
+    Pywal,
+    Gpl,
+    Ase,
+    Kpl,
+}
+
+/// Decode palette file content as utf8 text
+fn text(content: &[u8]) -> anyhow::Result<&str> {
+    std::str::from_utf8(content).context("palette is not valid utf8")
+}
+
+impl PaletteFormat {
+    /// Guess palette format from a filepath extension
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "gpl" => Some(Self::Gpl),
+            "ase" => Some(Self::Ase),
+            "kpl" => Some(Self::Kpl),
+            _ => None,
+        }
+    }
+    /// Guess palette format from file content
+    pub fn from_content(content: &[u8]) -> Self {
+        if content.starts_with(b"ASEF") {
+            return Self::Ase;
+        }
+        if content.starts_with(b"PK\x03\x04") {
+            return Self::Kpl;
+        }
+        let content = String::from_utf8_lossy(content);
+        let content = content.trim_start();
+        if content.starts_with("GIMP Palette") {
+            return Self::Gpl;
+        }
+        if content.starts_with('{') {
+            // pywal color schemes always contain a `special` section
+            return match content.contains("\"special\"") {
+                true => Self::Pywal,
+                false => Self::Json,
+            };
+        }
+        match toml::from_str::<toml::Table>(content).is_ok() {
+            true => Self::Toml,
+            false => Self::Yaml,
+        }
+    }
+    /// Determine format using extension and content (json files may be pywal)
+    pub fn detect(path: &str, content: &[u8]) -> Self {
+        match Self::from_path(path) {
+            Some(Self::Json) | None => Self::from_content(content),
+            Some(format) => format,
+        }
+    }
+    /// Parse palette from content in the specified format
+    pub fn parse(&self, path: &str, content: &[u8]) -> Result<Palette> {
+        let mut palette = self
+            .decode(path, content)
+            .map_err(|err| Error::PaletteParse {
+                format: *self,
+                source: err.into(),
+            })?;
+        palette.fill_missing()?;
+        Ok(palette)
+    }
+    fn decode(&self, path: &str, content: &[u8]) -> anyhow::Result<Palette> {
+        Ok(match self {
+            Self::Toml => toml::from_str(text(content)?)?,
+            Self::Json => serde_json::from_slice(content)?,
+            Self::Yaml => serde_norway::from_slice(content)?,
+            Self::Pywal => PywalColors::from_json(text(content)?)?.to_palette(Gradiant::Auto)?,
+            Self::Gpl => swatch::from_named_colors(path, swatch::from_gpl(text(content)?)?)?,
+            Self::Ase => swatch::from_named_colors(path, swatch::from_ase(content)?)?,
+            Self::Kpl => swatch::from_named_colors(path, swatch::from_kpl(content)?)?,
+        })
+    }
+    /// Serialize palette into the specified format
+    pub fn serialize(&self, palette: &Palette) -> Result<Vec<u8>> {
+        self.encode(palette).map_err(|err| Error::PaletteSerialize {
+            format: *self,
+            source: err.into(),
+        })
+    }
+    fn encode(&self, palette: &Palette) -> anyhow::Result<Vec<u8>> {
+        Ok(match self {
+            Self::Toml => toml::to_string(palette)?.into_bytes(),
+            Self::Json => serde_json::to_vec_pretty(palette)?,
+            Self::Yaml => serde_norway::to_string(palette)?.into_bytes(),
+            Self::Pywal => PywalColors::from_palette(palette).to_json()?.into_bytes(),
+            Self::Gpl => swatch::to_gpl(palette).into_bytes(),
+            Self::Ase => swatch::to_ase(palette),
+            Self::Kpl => swatch::to_kpl(palette)?,
+        })
+    }
+}
+
+impl Display for PaletteFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Toml => "toml",
+                Self::Json => "json",
+                Self::Yaml => "yaml",
+                Self::Pywal => "pywal",
+                Self::Gpl => "gpl",
+                Self::Ase => "ase",
+                Self::Kpl => "kpl",
+            }
+        )
+    }
+}
+
+impl FromStr for PaletteFormat {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "toml" => Ok(Self::Toml),
+            "json" => Ok(Self::Json),
+            "yaml" | "yml" => Ok(Self::Yaml),
+            "pywal" => Ok(Self::Pywal),
+            "gpl" => Ok(Self::Gpl),
+            "ase" => Ok(Self::Ase),
+            "kpl" => Ok(Self::Kpl),
+            _ => Err(format!("invalid palette format: {s:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::tests::palette;
+
+    fn hexes(palette: &Palette) -> Vec<String> {
+        palette
+            .colors()
+            .iter()
+            .flat_map(|c| [&c.primary, &c.text].into_iter().chain(&c.accents))
+            .map(|c| c.hex())
+            .collect()
+    }
+
+    #[test]
+    fn detect_prefers_extension_over_content() {
+        assert_eq!(
+            PaletteFormat::detect("a.yml", b"x = 1"),
+            PaletteFormat::Yaml
+        );
+        assert_eq!(PaletteFormat::detect("a.GPL", b""), PaletteFormat::Gpl);
+        assert_eq!(
+            PaletteFormat::detect("colors.json", br#"{"special": {}}"#),
+            PaletteFormat::Pywal
+        );
+        assert_eq!(PaletteFormat::detect("a.json", b"{}"), PaletteFormat::Json);
+    }
+
+    #[test]
+    fn detect_guesses_from_content() {
+        let detect = |content: &[u8]| PaletteFormat::detect("palette", content);
+        assert_eq!(detect(b"ASEF\x00\x01"), PaletteFormat::Ase);
+        assert_eq!(detect(b"PK\x03\x04"), PaletteFormat::Kpl);
+        assert_eq!(detect(b"\nGIMP Palette\n"), PaletteFormat::Gpl);
+        assert_eq!(detect(b"gradiant = \"standard\""), PaletteFormat::Toml);
+        assert_eq!(detect(b"gradiant: standard"), PaletteFormat::Yaml);
+    }
+
+    #[test]
+    fn parse_reports_the_format() {
+        let err = PaletteFormat::Json.parse("p.json", b"{}").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::PaletteParse {
+                format: PaletteFormat::Json,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn text_formats_round_trip() {
+        let palette = palette();
+        for format in [
+            PaletteFormat::Toml,
+            PaletteFormat::Json,
+            PaletteFormat::Yaml,
+        ] {
+            let content = format.serialize(&palette).unwrap();
+            assert_eq!(PaletteFormat::from_content(&content), format);
+            let restored = format.parse("palette", &content).unwrap();
+            assert_eq!(hexes(&restored), hexes(&palette), "{format}");
+            let light = |p: &Palette| p.light.as_ref().unwrap().color3.accents[4].hex();
+            assert_eq!(light(&restored), light(&palette), "{format}");
+        }
+    }
+}
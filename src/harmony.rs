@@ -0,0 +1,214 @@
+//! Color Harmony Rules and Primary Hue Variation
+
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+
+use palette::{Hsl, IntoColor, ShiftHue};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{de::Error as _, Deserialize, Serialize};
+
+use crate::color::Color;
+
+/// Lightness of the background derived from a seed color
+const BACKGROUND_LIGHTNESS: f32 = 0.12;
+/// Default minimum perceptual distance between primary colors
+const DEFAULT_MIN_DELTA_E: f32 = 12.0;
+/// Lightness shift of the second complementary color
+const COMPLEMENT_SHIFT: f32 = 0.2;
+
+/// Rules deriving related colors from a seed color
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Harmony {
+    /// Neighbouring hues 30° to either side
+    #[default]
+    Analogous,
+    /// Hues evenly spaced 120° apart
+    Triadic,
+    /// Opposite hue in two lightness variants
+    Complementary,
+    /// Both neighbours of the opposite hue
+    SplitComplementary,
+}
+
+impl Harmony {
+    /// Hue rotations (degrees) and lightness shifts of the derived colors
+    fn rules(&self) -> [(f32, f32); 2] {
+        match self {
+            Self::Analogous => [(30.0, 0.0), (-30.0, 0.0)],
+            Self::Triadic => [(120.0, 0.0), (240.0, 0.0)],
+            Self::Complementary => [(180.0, 0.0), (180.0, COMPLEMENT_SHIFT)],
+            Self::SplitComplementary => [(150.0, 0.0), (210.0, 0.0)],
+        }
+    }
+    /// Distinct hue rotations (degrees) relative to the seed
+    pub fn rotations(&self) -> Vec<f32> {
+        let mut rotations: Vec<f32> = self.rules().iter().map(|(hue, _)| *hue).collect();
+        rotations.dedup();
+        rotations
+    }
+    /// Derive colors related to the seed, starting with a dark background
+    pub fn derive(&self, seed: &Color) -> Vec<Color> {
+        let hsl: Hsl = seed.0.into_color();
+        let mut background = hsl;
+        background.lightness = BACKGROUND_LIGHTNESS;
+        let mut colors = vec![background.into_color()];
+        for (hue, lightness) in self.rules() {
+            let mut color = hsl.shift_hue(hue);
+            color.lightness = (color.lightness + lightness).clamp(0.0, 1.0);
+            colors.push(color.into_color());
+        }
+        colors.into_iter().map(Color).collect()
+    }
+}
+
+impl Display for Harmony {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Analogous => "analogous",
+                Self::Triadic => "triadic",
+                Self::Complementary => "complementary",
+                Self::SplitComplementary => "split-complementary",
+            }
+        )
+    }
+}
+
+impl FromStr for Harmony {
+    type Err = String;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "analogous" => Ok(Self::Analogous),
+            "triadic" => Ok(Self::Triadic),
+            "complementary" => Ok(Self::Complementary),
+            "split-complementary" => Ok(Self::SplitComplementary),
+            _ => Err(format!("invalid harmony: {s:?}")),
+        }
+    }
+}
+
+impl Serialize for Harmony {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Harmony {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s: String = Deserialize::deserialize(deserializer)?;
+        Harmony::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
+impl JsonSchema for Harmony {
+    fn schema_name() -> Cow<'static, str> {
+        "Harmony".into()
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "enum": ["analogous", "triadic", "complementary", "split-complementary"],
+        })
+    }
+}
+
+/// Hue variation enforced between the primary colors of a palette
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Variation {
+    /// Minimum CIEDE2000 distance required between primary colors
+    #[serde(default = "default_min_delta_e")]
+    pub min_delta_e: f32,
+    /// Harmony rule providing hues for substituted primaries
+    #[serde(default = "default_variation_harmony")]
+    pub harmony: Harmony,
+}
+
+fn default_min_delta_e() -> f32 {
+    DEFAULT_MIN_DELTA_E
+}
+
+fn default_variation_harmony() -> Harmony {
+    Harmony::Complementary
+}
+
+impl Default for Variation {
+    fn default() -> Self {
+        Self {
+            min_delta_e: DEFAULT_MIN_DELTA_E,
+            harmony: default_variation_harmony(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hsl(color: &Color) -> Hsl {
+        color.0.into_color()
+    }
+
+    /// Hue difference in degrees wrapped into `0..360`
+    fn hue_shift(from: &Color, to: &Color) -> f32 {
+        let shift = hsl(to).hue.into_positive_degrees() - hsl(from).hue.into_positive_degrees();
+        shift.rem_euclid(360.0)
+    }
+
+    #[test]
+    fn derive_rotates_seed_hue() {
+        let seed = Color::from_hex("#3A7BD5").unwrap();
+        for (harmony, shifts) in [
+            (Harmony::Analogous, [30.0, 330.0]),
+            (Harmony::Triadic, [120.0, 240.0]),
+            (Harmony::SplitComplementary, [150.0, 210.0]),
+        ] {
+            let colors = harmony.derive(&seed);
+            assert_eq!(colors.len(), 3, "{harmony}");
+            for (color, shift) in colors[1..].iter().zip(shifts) {
+                assert!((hue_shift(&seed, color) - shift).abs() < 0.5, "{harmony}");
+                let lightness = hsl(color).lightness - hsl(&seed).lightness;
+                assert!(lightness.abs() < 0.01, "{harmony}");
+            }
+        }
+    }
+
+    #[test]
+    fn derive_starts_with_dark_background() {
+        let seed = Color::from_hex("#3A7BD5").unwrap();
+        for harmony in [Harmony::Analogous, Harmony::Complementary] {
+            let background = &harmony.derive(&seed)[0];
+            assert!((hsl(background).lightness - BACKGROUND_LIGHTNESS).abs() < 0.01);
+            assert!(hue_shift(&seed, background).min(360.0 - hue_shift(&seed, background)) < 0.5);
+        }
+    }
+
+    #[test]
+    fn complementary_shifts_lightness_of_second_color() {
+        let seed = Color::from_hex("#3A7BD5").unwrap();
+        let colors = Harmony::Complementary.derive(&seed);
+        assert!((hue_shift(&seed, &colors[1]) - 180.0).abs() < 0.5);
+        let shift = hsl(&colors[2]).lightness - hsl(&colors[1]).lightness;
+        assert!((shift - COMPLEMENT_SHIFT).abs() < 0.01);
+        assert_eq!(Harmony::Complementary.rotations(), [180.0]);
+    }
+
+    #[test]
+    fn harmony_names_round_trip() {
+        for harmony in [
+            Harmony::Analogous,
+            Harmony::Triadic,
+            Harmony::Complementary,
+            Harmony::SplitComplementary,
+        ] {
+            assert_eq!(harmony.to_string().parse::<Harmony>(), Ok(harmony));
+        }
+        assert!("tetradic".parse::<Harmony>().is_err());
+    }
+}
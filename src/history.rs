@@ -0,0 +1,127 @@
+//! Palette History Tracking
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::color::Palette;
+use crate::output::write_atomic;
+
+/// Maximum number of entries retained in history
+const HISTORY_LIMIT: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix timestamp (seconds) of when the palette was applied
+    pub timestamp: u64,
+    /// Palette applied during the run
+    pub palette: Palette,
+}
+
+impl HistoryEntry {
+    /// Render timestamp as a human readable string
+    pub fn time(&self) -> String {
+        format_timestamp(self.timestamp)
+    }
+}
+
+/// Format a unix timestamp as an RFC 3339 UTC string (e.g. `2024-07-30T18:04:11Z`)
+fn format_timestamp(timestamp: u64) -> String {
+    let (days, secs) = (timestamp / 86400, timestamp % 86400);
+    let (year, month, day) = civil_from_days(days as i64);
+    let (hour, minute, second) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    format!("{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}Z")
+}
+
+/// Convert days since the unix epoch into a (year, month, day) date
+///
+/// Uses the proleptic gregorian calendar algorithm by Howard Hinnant.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    /// Previously applied palettes (newest first)
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Get filepath of the history store within the user state directory
+    fn path() -> Result<PathBuf> {
+        let dir = dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .context("failed to find state directory")?;
+        Ok(dir.join("wallbash").join("history.toml"))
+    }
+
+    /// Read history from the state directory or default when missing
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let s = std::fs::read_to_string(&path).context("failed to read history file")?;
+        toml::from_str(&s).context("failed to parse history file")
+    }
+
+    /// Write history back into the state directory
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).context("failed to make history dir")?;
+        }
+        let content = toml::to_string(self).context("failed to serialize history")?;
+        write_atomic(&path, &content).context("failed to write history file")
+    }
+
+    /// Record a newly applied palette as the most recent entry
+    pub fn push(&mut self, palette: Palette) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        self.entries.insert(0, HistoryEntry { timestamp, palette });
+        self.entries.truncate(HISTORY_LIMIT);
+    }
+
+    /// Retrieve entry by index (zero is the most recent)
+    pub fn get(&self, index: usize) -> Result<&HistoryEntry> {
+        self.entries
+            .get(index)
+            .ok_or_else(|| anyhow!("no history entry at index {index}"))
+    }
+
+    /// Drop the most recent entry and return the one before it
+    pub fn undo(&mut self) -> Result<&HistoryEntry> {
+        if self.entries.len() < 2 {
+            return Err(anyhow!("no previous palette in history"));
+        }
+        self.entries.remove(0);
+        self.get(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_formats_utc() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1722362651), "2024-07-30T18:04:11Z");
+    }
+}
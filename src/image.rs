@@ -1,40 +1,103 @@
 //! Image Operations and Analysis
 
-use std::path::PathBuf;
+use std::io::{BufRead, Cursor, Seek};
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
-use image::ImageReader;
+use image::{DynamicImage, ImageReader};
 use kmeans_colors::get_kmeans_hamerly;
 use palette::{cast::ComponentsAs, FromColor, Hsl, IntoColor, Srgb};
 use rayon::prelude::*;
 
 use crate::color::Color;
+use crate::error::{Error, Result};
 
+/// Path recorded for images not read from a file
+const IN_MEMORY: &str = "-";
+
+/// Decode image content read by an image reader
+fn decode<R: BufRead + Seek>(reader: ImageReader<R>, path: &Path) -> Result<DynamicImage> {
+    reader.decode().map_err(|source| Error::ImageDecode {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Decoded image pixels used for palette analysis
 pub struct RawImage {
     path: PathBuf,
     raw: Vec<Srgb<f32>>,
 }
 
 impl RawImage {
+    /// Read and decode an image, optionally shrinking it to `thumbnail` pixels
     pub fn new(path: &str, thumbnail: Option<u32>) -> Result<Self> {
-        // convert image to raw pixel buffer
         let path = PathBuf::from(path);
-        let mut img = ImageReader::open(&path)?.with_guessed_format()?.decode()?;
+        let read_err = |source| Error::ImageRead {
+            path: path.clone(),
+            source,
+        };
+        let reader = ImageReader::open(&path)
+            .and_then(|r| r.with_guessed_format())
+            .map_err(read_err)?;
+        let img = decode(reader, &path)?;
+        Ok(Self::from_dynamic_image(img, thumbnail).with_path(path))
+    }
+
+    /// Decode an in-memory image, detecting its format from the content
+    pub fn from_bytes(bytes: &[u8], thumbnail: Option<u32>) -> Result<Self> {
+        let path = PathBuf::from(IN_MEMORY);
+        let reader = ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(|source| Error::ImageRead {
+                path: path.clone(),
+                source,
+            })?;
+        let img = decode(reader, &path)?;
+        Ok(Self::from_dynamic_image(img, thumbnail))
+    }
+
+    /// Analyze an already decoded image
+    pub fn from_dynamic_image(mut img: DynamicImage, thumbnail: Option<u32>) -> Self {
         if let Some(size) = thumbnail {
             img = img.thumbnail(size, size);
         }
+        // convert image to raw pixel buffer
         let buf: Vec<u8> = img.into_rgb8().into_raw();
         // convert raw pixels into srgb objects
         let color_buffer: &[Srgb<u8>] = buf.components_as();
         let raw = color_buffer.par_iter().map(|x| x.into_format()).collect();
-        Ok(Self { path, raw })
+        Self {
+            path: PathBuf::from(IN_MEMORY),
+            raw,
+        }
+    }
+
+    /// Record the path the image came from (used as the palette `file`)
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = path.into();
+        self
     }
 
+    /// Path the image was read from (`-` for in-memory images)
     pub fn path(&self) -> &PathBuf {
         &self.path
     }
 
-    pub fn kmeans(&self, k: usize) -> Vec<Color> {
+    /// Cluster image pixels into `k` dominant colors
+    ///
+    /// Fails when the image has fewer than `k` pixels.
+    pub fn kmeans(&self, k: usize) -> Result<Vec<Color>> {
+        Ok(self.clusters(k)?.into_iter().map(|(c, _)| c).collect())
+    }
+
+    /// Cluster image pixels into `k` colors with the share of pixels in each
+    pub fn clusters(&self, k: usize) -> Result<Vec<(Color, f32)>> {
+        if self.raw.len() < k {
+            return Err(Error::EmptyImage {
+                pixels: self.raw.len(),
+                required: k,
+            });
+        }
         let runs = 3;
         let max_iter = 20;
         let converge = 0.0025;
@@ -44,22 +107,39 @@ impl RawImage {
         let result = (0..runs)
             .par_bridge()
             .map(|i| get_kmeans_hamerly(k, max_iter, converge, verbose, &self.raw, seed + i as u64))
-            .min_by(|r1, r2| r1.score.partial_cmp(&r2.score).unwrap())
-            .expect("no kmeans result available");
+            .min_by(|r1, r2| r1.score.total_cmp(&r2.score))
+            .ok_or(Error::NoKmeansResult)?;
+        if result.centroids.len() != k {
+            return Err(Error::ClusterCount {
+                expected: k,
+                found: result.centroids.len(),
+            });
+        }
+        let mut counts = vec![0usize; k];
+        for index in result.indices.iter() {
+            counts[*index as usize] += 1;
+        }
         // convert indexed colors back to hex-colors for output
-        result
+        let colors: Vec<(Color, f32)> = result
             .centroids
             .into_iter()
-            .map(|c| Color::from_color(c))
-            .collect()
+            .zip(counts)
+            .map(|(c, n)| (Color::from_color(c), n as f32 / self.raw.len() as f32))
+            .collect();
+        if colors.iter().any(|(c, _)| c.luminocity().is_nan()) {
+            return Err(Error::InvalidCluster);
+        }
+        Ok(colors)
     }
 
+    /// Average luminocity of all pixels (0.0 - 1.0)
     pub fn mean_luminocity(&self) -> f32 {
         let pixels: Vec<Color> = self.raw.par_iter().map(|c| Color::from_color(*c)).collect();
         let sum: f32 = pixels.par_iter().map(|c| c.luminocity()).sum();
         sum / pixels.len() as f32
     }
 
+    /// Average hsl saturation of all pixels (0.0 - 1.0)
     pub fn mean_saturation(&self) -> f32 {
         let pixels: Vec<Hsl<_>> = self.raw.par_iter().map(|c| (*c).into_color()).collect();
         let sum: f32 = pixels.par_iter().map(|c| c.saturation).sum();
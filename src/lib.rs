@@ -0,0 +1,43 @@
+//! Wallpaper Based Color Palette Generation
+//!
+//! Generate a [`Palette`] of primary, text and accent colors from an image
+//! and render it into configuration files with the template [`Engine`].
+//!
+//! ```no_run
+//! use wallbash::{Engine, Gradiant, Palette, RawImage};
+//!
+//! let image = RawImage::new("wallpaper.png", Some(256))?;
+//! let palette = Palette::create(&image, Gradiant::Auto)?;
+//! let kitty = Engine::new().render("background {{ color0.primary }}", &palette)?;
+//! # Ok::<(), wallbash::Error>(())
+//! ```
+
+// used by the `wallbash` binary and not part of the library interface
+#[doc(hidden)]
+pub mod builtin;
+pub mod color;
+#[doc(hidden)]
+pub mod config;
+pub mod error;
+pub mod format;
+pub mod harmony;
+#[doc(hidden)]
+pub mod history;
+pub mod image;
+pub mod material;
+#[doc(hidden)]
+pub mod output;
+#[doc(hidden)]
+pub mod preview;
+mod pywal;
+mod swatch;
+pub mod template;
+
+pub use ::image::DynamicImage;
+pub use color::{
+    Color, Gradiant, Palette, PaletteColor, PaletteSet, Primaries, Theme, ThemeMode, Variant,
+};
+pub use error::{Error, Result};
+pub use harmony::Harmony;
+pub use image::RawImage;
+pub use template::Engine;
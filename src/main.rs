@@ -1,24 +1,22 @@
-use anyhow::Result;
+use std::process::ExitCode;
+
 use clap::Parser;
 
 mod cli;
-mod color;
-mod config;
-mod image;
-mod template;
 
-use cli::{Cli, Commands};
+use cli::Cli;
 
 //TODO: creating new colors when not enough primary colors found
-//TODO: port templates somehow?
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     env_logger::init();
 
     let cli = Cli::parse();
-    match cli.command {
-        Commands::Run(args) => args.run(),
-        Commands::Fill(args) => args.fill(),
-        Commands::Generate(args) => args.generate(),
+    match cli::run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            cli::exit_code(&err)
+        }
     }
 }
@@ -0,0 +1,510 @@
+//! Material 3 Tonal Palettes and Color Schemes
+//!
+//! Colors are described in HCT (CAM16 hue and chroma with CIELAB lightness
+//! as tone) and schemes follow the Material 3 "tonal spot" variant.
+
+use std::f64::consts::PI;
+
+use palette::{LinSrgb, Srgb};
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+
+/// sRGB (linear, 0-100) to XYZ conversion matrix
+const SRGB_TO_XYZ: [[f64; 3]; 3] = [
+    [0.41233895, 0.35762064, 0.18051042],
+    [0.2126, 0.7152, 0.0722],
+    [0.01932141, 0.11916382, 0.95034478],
+];
+/// XYZ to sRGB (linear, 0-100) conversion matrix
+const XYZ_TO_SRGB: [[f64; 3]; 3] = [
+    [
+        3.2413774792388685,
+        -1.5376652402851851,
+        -0.49885366846268053,
+    ],
+    [-0.9691452513005321, 1.8758853451067872, 0.04156585616912061],
+    [
+        0.05562093689691305,
+        -0.20395524564742123,
+        1.0571799111220335,
+    ],
+];
+/// XYZ to CAM16 cone response matrix
+const XYZ_TO_CAM16: [[f64; 3]; 3] = [
+    [0.401288, 0.650173, -0.051461],
+    [-0.250268, 1.204414, 0.045854],
+    [-0.002079, 0.048952, 0.953127],
+];
+/// CAM16 cone response to XYZ matrix
+const CAM16_TO_XYZ: [[f64; 3]; 3] = [
+    [1.86206786, -1.01125463, 0.14918677],
+    [0.38752654, 0.62144744, -0.00897398],
+    [-0.01584150, -0.03412294, 1.04996444],
+];
+/// D65 white point
+const WHITE_POINT: [f64; 3] = [95.047, 100.0, 108.883];
+/// Iterations used by each binary search when solving HCT colors
+const SOLVER_STEPS: usize = 24;
+
+fn matmul(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+/// CIELAB lightness from relative luminance (0-100)
+fn lstar_from_y(y: f64) -> f64 {
+    let y = y / 100.0;
+    match y > 216.0 / 24389.0 {
+        true => 116.0 * y.cbrt() - 16.0,
+        false => 24389.0 / 27.0 * y,
+    }
+}
+
+/// Relative luminance (0-100) from CIELAB lightness
+fn y_from_lstar(lstar: f64) -> f64 {
+    let ft = (lstar + 16.0) / 116.0;
+    let ft3 = ft * ft * ft;
+    100.0
+        * match ft3 > 216.0 / 24389.0 {
+            true => ft3,
+            false => (116.0 * ft - 16.0) / (24389.0 / 27.0),
+        }
+}
+
+/// CAM16 viewing conditions (Material defaults: average surround, L* 50 background)
+struct Viewing {
+    n: f64,
+    aw: f64,
+    nbb: f64,
+    c: f64,
+    nc: f64,
+    z: f64,
+    fl: f64,
+    rgb_d: [f64; 3],
+}
+
+impl Viewing {
+    fn standard() -> Self {
+        let adapting = 200.0 / PI * y_from_lstar(50.0) / 100.0;
+        let rgb_w = matmul(&XYZ_TO_CAM16, WHITE_POINT);
+        let f = 1.0;
+        let c = 0.69;
+        let d = (f * (1.0 - (1.0 / 3.6) * ((-adapting - 42.0) / 92.0).exp())).clamp(0.0, 1.0);
+        let rgb_d = rgb_w.map(|w| d * (100.0 / w) + 1.0 - d);
+        let k = 1.0 / (5.0 * adapting + 1.0);
+        let k4 = k.powi(4);
+        let fl = k4 * adapting + 0.1 * (1.0 - k4).powi(2) * (5.0 * adapting).cbrt();
+        let n = y_from_lstar(50.0) / WHITE_POINT[1];
+        let z = 1.48 + n.sqrt();
+        let nbb = 0.725 / n.powf(0.2);
+        let rgb_a = [0, 1, 2].map(|i| {
+            let af = (fl * rgb_d[i] * rgb_w[i] / 100.0).powf(0.42);
+            400.0 * af / (af + 27.13)
+        });
+        let aw = (2.0 * rgb_a[0] + rgb_a[1] + 0.05 * rgb_a[2]) * nbb;
+        Self {
+            n,
+            aw,
+            nbb,
+            c,
+            nc: f,
+            z,
+            fl,
+            rgb_d,
+        }
+    }
+
+    /// CAM16 hue (degrees) and chroma of an XYZ color
+    fn hue_chroma(&self, xyz: [f64; 3]) -> (f64, f64) {
+        let rgb_c = matmul(&XYZ_TO_CAM16, xyz);
+        let rgb_a = [0, 1, 2].map(|i| {
+            let d = self.rgb_d[i] * rgb_c[i];
+            let af = (self.fl * d.abs() / 100.0).powf(0.42);
+            d.signum() * 400.0 * af / (af + 27.13)
+        });
+        let [r, g, b] = rgb_a;
+        let a = (11.0 * r - 12.0 * g + b) / 11.0;
+        let bb = (r + g - 2.0 * b) / 9.0;
+        let u = (20.0 * r + 20.0 * g + 21.0 * b) / 20.0;
+        let p2 = (40.0 * r + 20.0 * g + b) / 20.0;
+        let hue = bb.atan2(a).to_degrees().rem_euclid(360.0);
+        let j = 100.0 * (p2 * self.nbb / self.aw).powf(self.c * self.z);
+        let hue_prime = if hue < 20.14 { hue + 360.0 } else { hue };
+        let e_hue = 0.25 * ((hue_prime.to_radians() + 2.0).cos() + 3.8);
+        let p1 = 50000.0 / 13.0 * e_hue * self.nc * self.nbb;
+        let t = p1 * a.hypot(bb) / (u + 0.305);
+        let alpha = t.powf(0.9) * (1.64 - 0.29f64.powf(self.n)).powf(0.73);
+        (hue, alpha * (j / 100.0).sqrt())
+    }
+
+    /// XYZ color from CAM16 lightness (J), chroma and hue
+    fn xyz(&self, j: f64, chroma: f64, hue: f64) -> [f64; 3] {
+        let alpha = match chroma == 0.0 || j == 0.0 {
+            true => 0.0,
+            false => chroma / (j / 100.0).sqrt(),
+        };
+        let t = (alpha / (1.64 - 0.29f64.powf(self.n)).powf(0.73)).powf(1.0 / 0.9);
+        let h = hue.to_radians();
+        let e_hue = 0.25 * ((h + 2.0).cos() + 3.8);
+        let ac = self.aw * (j / 100.0).powf(1.0 / self.c / self.z);
+        let p1 = e_hue * (50000.0 / 13.0) * self.nc * self.nbb;
+        let p2 = ac / self.nbb;
+        let (sin, cos) = h.sin_cos();
+        let gamma = 23.0 * (p2 + 0.305) * t / (23.0 * p1 + 11.0 * t * cos + 108.0 * t * sin);
+        let (a, b) = (gamma * cos, gamma * sin);
+        let rgb_a = [
+            (460.0 * p2 + 451.0 * a + 288.0 * b) / 1403.0,
+            (460.0 * p2 - 891.0 * a - 261.0 * b) / 1403.0,
+            (460.0 * p2 - 220.0 * a - 6300.0 * b) / 1403.0,
+        ];
+        let rgb_f = [0, 1, 2].map(|i| {
+            let ra = rgb_a[i];
+            let base = (27.13 * ra.abs() / (400.0 - ra.abs())).max(0.0);
+            ra.signum() * (100.0 / self.fl) * base.powf(1.0 / 0.42) / self.rgb_d[i]
+        });
+        matmul(&CAM16_TO_XYZ, rgb_f)
+    }
+}
+
+/// Color described by CAM16 hue, chroma and CIELAB lightness (tone)
+#[derive(Debug, Clone, Copy)]
+pub struct Hct {
+    pub hue: f64,
+    pub chroma: f64,
+    pub tone: f64,
+}
+
+impl Hct {
+    /// Convert an srgb color into HCT
+    pub fn from_color(color: &Color) -> Self {
+        let lin: LinSrgb<f64> = color.0.into_format::<f64>().into_linear();
+        let xyz = matmul(
+            &SRGB_TO_XYZ,
+            [lin.red * 100.0, lin.green * 100.0, lin.blue * 100.0],
+        );
+        let (hue, chroma) = Viewing::standard().hue_chroma(xyz);
+        Self {
+            hue,
+            chroma,
+            tone: lstar_from_y(xyz[1]),
+        }
+    }
+
+    /// Convert into the closest srgb color, reducing chroma to stay in gamut
+    pub fn to_color(&self) -> Color {
+        let tone = self.tone.clamp(0.0, 100.0);
+        let gray = || {
+            let y = y_from_lstar(tone);
+            xyz_to_color([y / 100.0 * WHITE_POINT[0], y, y / 100.0 * WHITE_POINT[2]])
+        };
+        if self.chroma < 0.0001 || !(0.0001..=99.9999).contains(&tone) {
+            return gray();
+        }
+        let viewing = Viewing::standard();
+        if let Some(xyz) = solve_tone(&viewing, self.hue, self.chroma, tone) {
+            return xyz_to_color(xyz);
+        }
+        // search for the highest chroma within gamut
+        let (mut low, mut high) = (0.0, self.chroma);
+        let mut best = None;
+        for _ in 0..SOLVER_STEPS {
+            let mid = (low + high) / 2.0;
+            match solve_tone(&viewing, self.hue, mid, tone) {
+                Some(xyz) => {
+                    best = Some(xyz);
+                    low = mid;
+                }
+                None => high = mid,
+            }
+        }
+        best.map(xyz_to_color).unwrap_or_else(gray)
+    }
+}
+
+/// Find an in-gamut XYZ color with the hue and chroma at the requested tone
+fn solve_tone(viewing: &Viewing, hue: f64, chroma: f64, tone: f64) -> Option<[f64; 3]> {
+    let (mut low, mut high) = (0.0, 100.0);
+    let mut xyz = [0.0; 3];
+    for _ in 0..SOLVER_STEPS {
+        let j = (low + high) / 2.0;
+        xyz = viewing.xyz(j, chroma, hue);
+        match lstar_from_y(xyz[1]) < tone {
+            true => low = j,
+            false => high = j,
+        }
+    }
+    let rgb = matmul(&XYZ_TO_SRGB, xyz);
+    let in_gamut = rgb.iter().all(|c| (-0.01..=100.01).contains(c));
+    let accurate = (lstar_from_y(xyz[1]) - tone).abs() < 0.5;
+    (in_gamut && accurate).then_some(xyz)
+}
+
+fn xyz_to_color(xyz: [f64; 3]) -> Color {
+    let [r, g, b] = matmul(&XYZ_TO_SRGB, xyz).map(|c| (c / 100.0).clamp(0.0, 1.0));
+    let srgb: Srgb<f64> = Srgb::from_linear(LinSrgb::new(r, g, b));
+    Color(srgb.into_format())
+}
+
+/// Colors of a single hue and chroma across all tones
+#[derive(Debug, Clone, Copy)]
+pub struct TonalPalette {
+    pub hue: f64,
+    pub chroma: f64,
+}
+
+impl TonalPalette {
+    pub fn new(hue: f64, chroma: f64) -> Self {
+        Self {
+            hue: hue.rem_euclid(360.0),
+            chroma,
+        }
+    }
+    /// Color at the specified tone (0 black - 100 white)
+    pub fn tone(&self, tone: f64) -> Color {
+        Hct {
+            hue: self.hue,
+            chroma: self.chroma,
+            tone,
+        }
+        .to_color()
+    }
+}
+
+/// Material 3 color roles for a single brightness
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Scheme {
+    pub primary: Color,
+    pub on_primary: Color,
+    pub primary_container: Color,
+    pub on_primary_container: Color,
+    pub inverse_primary: Color,
+    pub secondary: Color,
+    pub on_secondary: Color,
+    pub secondary_container: Color,
+    pub on_secondary_container: Color,
+    pub tertiary: Color,
+    pub on_tertiary: Color,
+    pub tertiary_container: Color,
+    pub on_tertiary_container: Color,
+    pub error: Color,
+    pub on_error: Color,
+    pub error_container: Color,
+    pub on_error_container: Color,
+    pub background: Color,
+    pub on_background: Color,
+    pub surface: Color,
+    pub on_surface: Color,
+    pub surface_variant: Color,
+    pub on_surface_variant: Color,
+    pub surface_dim: Color,
+    pub surface_bright: Color,
+    pub surface_container_lowest: Color,
+    pub surface_container_low: Color,
+    pub surface_container: Color,
+    pub surface_container_high: Color,
+    pub surface_container_highest: Color,
+    pub inverse_surface: Color,
+    pub inverse_on_surface: Color,
+    pub outline: Color,
+    pub outline_variant: Color,
+    pub shadow: Color,
+    pub scrim: Color,
+}
+
+/// Tonal palettes of the tonal spot scheme variant
+struct Palettes {
+    primary: TonalPalette,
+    secondary: TonalPalette,
+    tertiary: TonalPalette,
+    neutral: TonalPalette,
+    neutral_variant: TonalPalette,
+    error: TonalPalette,
+}
+
+impl Palettes {
+    fn new(source: &Hct) -> Self {
+        Self {
+            primary: TonalPalette::new(source.hue, 36.0),
+            secondary: TonalPalette::new(source.hue, 16.0),
+            tertiary: TonalPalette::new(source.hue + 60.0, 24.0),
+            neutral: TonalPalette::new(source.hue, 6.0),
+            neutral_variant: TonalPalette::new(source.hue, 8.0),
+            error: TonalPalette::new(25.0, 84.0),
+        }
+    }
+}
+
+impl Scheme {
+    /// Interpolate every color role towards another scheme in Oklab
+    pub fn mix(&self, other: &Self, t: f32) -> Self {
+        macro_rules! mix {
+            ($($role:ident),*) => {
+                Self { $($role: self.$role.mix(&other.$role, t)),* }
+            };
+        }
+        mix!(
+            primary,
+            on_primary,
+            primary_container,
+            on_primary_container,
+            inverse_primary,
+            secondary,
+            on_secondary,
+            secondary_container,
+            on_secondary_container,
+            tertiary,
+            on_tertiary,
+            tertiary_container,
+            on_tertiary_container,
+            error,
+            on_error,
+            error_container,
+            on_error_container,
+            background,
+            on_background,
+            surface,
+            on_surface,
+            surface_variant,
+            on_surface_variant,
+            surface_dim,
+            surface_bright,
+            surface_container_lowest,
+            surface_container_low,
+            surface_container,
+            surface_container_high,
+            surface_container_highest,
+            inverse_surface,
+            inverse_on_surface,
+            outline,
+            outline_variant,
+            shadow,
+            scrim
+        )
+    }
+    fn new(p: &Palettes, dark: bool) -> Self {
+        // tone used in the light scheme and in the dark scheme
+        let t = |light: f64, dark_tone: f64| if dark { dark_tone } else { light };
+        Self {
+            primary: p.primary.tone(t(40.0, 80.0)),
+            on_primary: p.primary.tone(t(100.0, 20.0)),
+            primary_container: p.primary.tone(t(90.0, 30.0)),
+            on_primary_container: p.primary.tone(t(10.0, 90.0)),
+            inverse_primary: p.primary.tone(t(80.0, 40.0)),
+            secondary: p.secondary.tone(t(40.0, 80.0)),
+            on_secondary: p.secondary.tone(t(100.0, 20.0)),
+            secondary_container: p.secondary.tone(t(90.0, 30.0)),
+            on_secondary_container: p.secondary.tone(t(10.0, 90.0)),
+            tertiary: p.tertiary.tone(t(40.0, 80.0)),
+            on_tertiary: p.tertiary.tone(t(100.0, 20.0)),
+            tertiary_container: p.tertiary.tone(t(90.0, 30.0)),
+            on_tertiary_container: p.tertiary.tone(t(10.0, 90.0)),
+            error: p.error.tone(t(40.0, 80.0)),
+            on_error: p.error.tone(t(100.0, 20.0)),
+            error_container: p.error.tone(t(90.0, 30.0)),
+            on_error_container: p.error.tone(t(10.0, 90.0)),
+            background: p.neutral.tone(t(98.0, 6.0)),
+            on_background: p.neutral.tone(t(10.0, 90.0)),
+            surface: p.neutral.tone(t(98.0, 6.0)),
+            on_surface: p.neutral.tone(t(10.0, 90.0)),
+            surface_variant: p.neutral_variant.tone(t(90.0, 30.0)),
+            on_surface_variant: p.neutral_variant.tone(t(30.0, 80.0)),
+            surface_dim: p.neutral.tone(t(87.0, 6.0)),
+            surface_bright: p.neutral.tone(t(98.0, 24.0)),
+            surface_container_lowest: p.neutral.tone(t(100.0, 4.0)),
+            surface_container_low: p.neutral.tone(t(96.0, 10.0)),
+            surface_container: p.neutral.tone(t(94.0, 12.0)),
+            surface_container_high: p.neutral.tone(t(92.0, 17.0)),
+            surface_container_highest: p.neutral.tone(t(90.0, 22.0)),
+            inverse_surface: p.neutral.tone(t(20.0, 90.0)),
+            inverse_on_surface: p.neutral.tone(t(95.0, 20.0)),
+            outline: p.neutral_variant.tone(t(50.0, 60.0)),
+            outline_variant: p.neutral_variant.tone(t(80.0, 30.0)),
+            shadow: p.neutral.tone(0.0),
+            scrim: p.neutral.tone(0.0),
+        }
+    }
+}
+
+/// Material 3 light and dark schemes generated from a source color
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Material {
+    /// Source color the schemes were generated from
+    pub source: Color,
+    pub light: Scheme,
+    pub dark: Scheme,
+}
+
+impl Material {
+    /// Generate tonal spot schemes from a source color
+    pub fn new(source: &Color) -> Self {
+        let palettes = Palettes::new(&Hct::from_color(source));
+        Self {
+            source: source.clone(),
+            light: Scheme::new(&palettes, false),
+            dark: Scheme::new(&palettes, true),
+        }
+    }
+    /// Interpolate source color and schemes towards another material in Oklab
+    pub fn mix(&self, other: &Self, t: f32) -> Self {
+        Self {
+            source: self.source.mix(&other.source, t),
+            light: self.light.mix(&other.light, t),
+            dark: self.dark.mix(&other.dark, t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(color: &str) -> Color {
+        Color::from_hex(color).unwrap()
+    }
+
+    #[test]
+    fn hct_round_trips_srgb_colors() {
+        for color in ["#6750A4", "#3A7BD5", "#101820", "#F0F0F0", "#FF0000"] {
+            let hct = Hct::from_color(&hex(color));
+            assert_eq!(hct.to_color().hex(), color);
+        }
+        let hct = Hct::from_color(&hex("#6750A4"));
+        assert!((hct.tone - 40.0).abs() < 0.5, "{hct:?}");
+    }
+
+    #[test]
+    fn tonal_palette_spans_black_to_white() {
+        let gray = TonalPalette::new(0.0, 0.0);
+        assert_eq!(gray.tone(0.0).hex(), "#000000");
+        assert_eq!(gray.tone(50.0).hex(), "#777777");
+        assert_eq!(gray.tone(100.0).hex(), "#FFFFFF");
+        let source = Hct::from_color(&hex("#6750A4"));
+        let palette = TonalPalette::new(source.hue, source.chroma);
+        assert_eq!(palette.tone(source.tone).hex(), "#6750A4");
+        for tone in [10.0, 30.0, 60.0, 90.0] {
+            let hct = Hct::from_color(&palette.tone(tone));
+            assert!((hct.tone - tone).abs() < 0.5, "tone {tone}: {hct:?}");
+        }
+    }
+
+    #[test]
+    fn scheme_matches_reference() {
+        let material = Material::new(&hex("#6750A4"));
+        assert_eq!(material.light.primary.hex(), "#65558F");
+        assert_eq!(material.light.primary_container.hex(), "#E9DDFF");
+        assert_eq!(material.dark.primary.hex(), "#CFBDFE");
+    }
+
+    #[test]
+    fn scheme_roles_use_tones_for_brightness() {
+        let material = Material::new(&hex("#6750A4"));
+        let tone = |color: &Color| Hct::from_color(color).tone.round();
+        assert_eq!(material.light.on_primary.hex(), "#FFFFFF");
+        assert_eq!(tone(&material.dark.on_primary), 20.0);
+        assert_eq!(tone(&material.light.primary), 40.0);
+        assert_eq!(tone(&material.dark.primary), 80.0);
+        assert_eq!(material.light.inverse_primary, material.dark.primary);
+        assert_eq!(material.dark.shadow.hex(), "#000000");
+    }
+}
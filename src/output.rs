@@ -0,0 +1,263 @@
+//! Template Output Writing and Backups
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+
+/// Filename recording the target path of a backup run entry
+const TARGET_FILE: &str = "target";
+/// Filename holding the previous target content of a backup run entry
+///
+/// The file is missing when the target did not exist before the run.
+const CONTENT_FILE: &str = "content";
+
+/// Write content to the target path atomically
+///
+/// Content is written to a temporary file in the same directory and then
+/// renamed over the target, preserving the original file permissions.
+/// Symlinked targets are resolved so the link itself is kept in place.
+pub fn write_atomic(target: &Path, content: impl AsRef<[u8]>) -> Result<()> {
+    let resolved = match target.exists() {
+        true => std::fs::canonicalize(target).context("failed to resolve target")?,
+        false => target.to_path_buf(),
+    };
+    let target = resolved.as_path();
+    let parent = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let name = target
+        .file_name()
+        .ok_or_else(|| anyhow!("invalid target path: {target:?}"))?
+        .to_string_lossy();
+    let tmp = parent.join(format!(".{name}.{}.tmp", std::process::id()));
+    let result = (|| {
+        std::fs::write(&tmp, content).context("failed to write temp file")?;
+        if let Ok(meta) = std::fs::metadata(target) {
+            std::fs::set_permissions(&tmp, meta.permissions())
+                .context("failed to copy file permissions")?;
+        }
+        std::fs::rename(&tmp, target).context("failed to replace target")
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Previous template renders kept in the cache directory
+pub struct Backups {
+    root: PathBuf,
+}
+
+impl Backups {
+    /// Open backup directory within the user cache directory
+    pub fn new() -> Result<Self> {
+        let root = dirs::cache_dir()
+            .context("failed to find cache directory")?
+            .join("wallbash")
+            .join("backups");
+        Ok(Self::open(root))
+    }
+
+    /// Open backup directory at the specified path
+    pub fn open(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Generate a new identifier for the current run
+    pub fn run_id() -> u128 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default()
+    }
+
+    /// Backup directory for a template
+    ///
+    /// Expanded template names may contain `/`, so `%` and `/` are
+    /// percent-encoded to keep distinct names in distinct directories.
+    fn dir(&self, name: &str) -> PathBuf {
+        self.root.join(name.replace('%', "%25").replace('/', "%2F"))
+    }
+
+    /// List run identifiers of backups within a template backup directory
+    fn template_runs(dir: &Path) -> Result<Vec<u128>> {
+        if !dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut runs: Vec<u128> = std::fs::read_dir(dir)
+            .context("failed to read backup directory")?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().and_then(|s| s.parse().ok()))
+            .collect();
+        runs.sort();
+        Ok(runs)
+    }
+
+    /// List encoded template directory names with stored backups
+    fn templates(&self) -> Result<Vec<String>> {
+        if !self.root.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut names: Vec<String> = std::fs::read_dir(&self.root)
+            .context("failed to read backup directory")?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(|s| s.to_owned()))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// List all run identifiers with backups (newest first)
+    pub fn runs(&self) -> Result<Vec<u128>> {
+        let mut runs = vec![];
+        for name in self.templates()? {
+            runs.extend(Self::template_runs(&self.root.join(name))?);
+        }
+        runs.sort();
+        runs.dedup();
+        runs.reverse();
+        Ok(runs)
+    }
+
+    /// Store the current contents of a template target before it is overwritten
+    ///
+    /// Each run records its own target path, and targets missing before the
+    /// run are marked by omitting the content. Only the newest `keep`
+    /// backups are retained for each template.
+    pub fn save(&self, run: u128, name: &str, target: &Path, keep: usize) -> Result<()> {
+        let dir = self.dir(name);
+        let entry = dir.join(run.to_string());
+        std::fs::create_dir_all(&entry).context("failed to make backup dir")?;
+        let target = std::path::absolute(target).context("failed to resolve target")?;
+        write_atomic(
+            &entry.join(TARGET_FILE),
+            target.to_string_lossy().as_bytes(),
+        )
+        .context("failed to record backup target")?;
+        if target.is_file() {
+            std::fs::copy(&target, entry.join(CONTENT_FILE))
+                .context("failed to copy target to backup")?;
+        }
+        let runs = Self::template_runs(&dir)?;
+        for old in runs.iter().take(runs.len().saturating_sub(keep)) {
+            log::debug!("{name:?} removing old backup {old}");
+            std::fs::remove_dir_all(dir.join(old.to_string()))
+                .context("failed to remove old backup")?;
+        }
+        Ok(())
+    }
+
+    /// Restore all template targets to their state before the specified run
+    ///
+    /// Targets that did not exist before the run are removed.
+    pub fn restore(&self, run: u128) -> Result<()> {
+        let mut restored = 0;
+        for name in self.templates()? {
+            let entry = self.root.join(&name).join(run.to_string());
+            if !entry.is_dir() {
+                continue;
+            }
+            let target = std::fs::read_to_string(entry.join(TARGET_FILE))
+                .context(format!("{name:?} missing backup target"))?;
+            let target = Path::new(&target);
+            let backup = entry.join(CONTENT_FILE);
+            if backup.is_file() {
+                log::info!("restoring template {name:?} => {target:?}");
+                let content =
+                    std::fs::read(&backup).context(format!("{name:?} failed to read backup"))?;
+                write_atomic(target, &content)
+                    .context(format!("{name:?} failed to restore backup"))?;
+            } else if target.is_file() {
+                log::info!("removing template {name:?} target {target:?} absent before run");
+                std::fs::remove_file(target)
+                    .context(format!("{name:?} failed to remove target"))?;
+            }
+            restored += 1;
+        }
+        if restored == 0 {
+            return Err(anyhow!("no backups found for run {run}"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_atomic_replaces_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("kitty.conf");
+        std::fs::write(&target, "old").unwrap();
+        write_atomic(&target, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_atomic_keeps_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let real = dir.path().join("dotfiles.conf");
+        let link = dir.path().join("kitty.conf");
+        std::fs::write(&real, "old").unwrap();
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        write_atomic(&link, "new").unwrap();
+        assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_to_string(&real).unwrap(), "new");
+    }
+
+    #[test]
+    fn restore_every_target_of_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let backups = Backups::open(dir.path().join("backups"));
+        let first = dir.path().join("first.css");
+        let second = dir.path().join("second.css");
+        std::fs::write(&first, "first").unwrap();
+        backups.save(1, "gtk/first", &first, 2).unwrap();
+        backups.save(1, "gtk/second", &second, 2).unwrap();
+        write_atomic(&first, "changed").unwrap();
+        write_atomic(&second, "created").unwrap();
+        assert_eq!(backups.runs().unwrap(), vec![1]);
+        backups.restore(1).unwrap();
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "first");
+        assert!(!second.exists());
+    }
+
+    #[test]
+    fn template_names_do_not_collide() {
+        let dir = tempfile::tempdir().unwrap();
+        let backups = Backups::open(dir.path().join("backups"));
+        let (slash, percent) = (dir.path().join("slash"), dir.path().join("percent"));
+        std::fs::write(&slash, "slash").unwrap();
+        std::fs::write(&percent, "percent").unwrap();
+        backups.save(1, "a/b", &slash, 1).unwrap();
+        backups.save(1, "a%2Fb", &percent, 1).unwrap();
+        std::fs::write(&slash, "").unwrap();
+        std::fs::write(&percent, "").unwrap();
+        backups.restore(1).unwrap();
+        assert_eq!(std::fs::read_to_string(&slash).unwrap(), "slash");
+        assert_eq!(std::fs::read_to_string(&percent).unwrap(), "percent");
+    }
+
+    #[test]
+    fn save_keeps_newest_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let backups = Backups::open(dir.path().join("backups"));
+        let target = dir.path().join("kitty.conf");
+        for run in 1..=3 {
+            std::fs::write(&target, run.to_string()).unwrap();
+            backups.save(run, "kitty", &target, 2).unwrap();
+        }
+        assert_eq!(backups.runs().unwrap(), vec![3, 2]);
+        assert!(backups.restore(1).is_err());
+        backups.restore(2).unwrap();
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "2");
+    }
+}
@@ -0,0 +1,194 @@
+//! Terminal and Image Palette Previews
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::{imageops, Rgb, RgbImage};
+
+use crate::color::{Color, Palette, PaletteColor, Theme};
+
+/// Number of accent swatches rendered per row
+const ACCENTS_PER_ROW: usize = 3;
+/// Spacing placed between palettes rendered side by side
+const COLUMN_GAP: usize = 4;
+/// Pixel size of a single swatch in the preview sheet
+const CELL_SIZE: u32 = 32;
+/// Pixel spacing between swatches in the preview sheet
+const CELL_GAP: u32 = 4;
+/// Pixel margin surrounding the preview sheet
+const SHEET_MARGIN: u32 = 12;
+
+/// Generate truecolor background escape sequence
+fn bg(c: &Color) -> String {
+    let (r, g, b) = c.rgb();
+    format!("\x1b[48;2;{r};{g};{b}m")
+}
+
+/// Generate truecolor foreground escape sequence
+fn fg(c: &Color) -> String {
+    let (r, g, b) = c.rgb();
+    format!("\x1b[38;2;{r};{g};{b}m")
+}
+
+/// Render a solid swatch of the specified color
+fn swatch(c: &Color) -> String {
+    format!("{}    \x1b[0m", bg(c))
+}
+
+/// Calculate visible width of a line ignoring escape sequences
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut escaped = false;
+    for c in line.chars() {
+        match (escaped, c) {
+            (false, '\x1b') => escaped = true,
+            (true, 'm') => escaped = false,
+            (true, _) => {}
+            (false, _) => width += 1,
+        }
+    }
+    width
+}
+
+/// Grade contrast ratio according to WCAG thresholds
+fn grade(ratio: f32) -> &'static str {
+    match ratio {
+        r if r >= 7.0 => "AAA",
+        r if r >= 4.5 => "AA",
+        r if r >= 3.0 => "AA18",
+        _ => "FAIL",
+    }
+}
+
+/// Render preview lines for a single palette color
+fn render_color(name: &str, color: &PaletteColor) -> Vec<String> {
+    let ratio = color.text.contrast(&color.primary);
+    let sample = format!("{}{} Aa \x1b[0m", bg(&color.primary), fg(&color.text));
+    let mut lines = vec![format!(
+        "{name} {} {}  {sample} {}  {ratio:>5.2}:1 {}",
+        swatch(&color.primary),
+        color.primary,
+        color.text,
+        grade(ratio),
+    )];
+    for (row, chunk) in color.accents.chunks(ACCENTS_PER_ROW).enumerate() {
+        let accents: Vec<String> = chunk
+            .iter()
+            .enumerate()
+            .map(|(n, c)| {
+                let n = row * ACCENTS_PER_ROW + n + 1;
+                format!("{n} {} {c}", swatch(c))
+            })
+            .collect();
+        lines.push(format!("       {}", accents.join("  ")));
+    }
+    lines
+}
+
+/// Render preview lines for an entire palette
+pub fn render(palette: &Palette) -> Vec<String> {
+    let mut lines = vec![
+        format!("{} ({}, {})", palette.file, palette.theme, palette.gradiant),
+        String::new(),
+    ];
+    let colors = [
+        ("color0", &palette.color0),
+        ("color1", &palette.color1),
+        ("color2", &palette.color2),
+        ("color3", &palette.color3),
+    ];
+    for (name, color) in colors {
+        lines.extend(render_color(name, color));
+        lines.push(String::new());
+    }
+    lines
+}
+
+/// Join multiple rendered palettes into side by side columns
+pub fn columns(palettes: Vec<Vec<String>>) -> Vec<String> {
+    let widths: Vec<usize> = palettes
+        .iter()
+        .map(|p| p.iter().map(|l| visible_width(l)).max().unwrap_or(0))
+        .collect();
+    let height = palettes.iter().map(|p| p.len()).max().unwrap_or(0);
+    (0..height)
+        .map(|n| {
+            let mut line = String::new();
+            for (palette, width) in palettes.iter().zip(widths.iter()) {
+                let cell = palette.get(n).map(|s| s.as_str()).unwrap_or("");
+                let pad = width - visible_width(cell) + COLUMN_GAP;
+                line.push_str(cell);
+                line.push_str(&" ".repeat(pad));
+            }
+            line.trim_end().to_owned()
+        })
+        .collect()
+}
+
+/// Fill a square swatch cell at the specified position
+fn fill_cell(img: &mut RgbImage, x: u32, y: u32, color: &Color) {
+    let (r, g, b) = color.rgb();
+    for px in x..x + CELL_SIZE {
+        for py in y..y + CELL_SIZE {
+            img.put_pixel(px, py, Rgb([r, g, b]));
+        }
+    }
+}
+
+/// Render palette swatch sheet beside a thumbnail of the source wallpaper
+///
+/// Each row contains the primary, text and nine accent colors of a single
+/// palette color. The thumbnail is skipped when the source image is missing.
+pub fn sheet(palette: &Palette) -> RgbImage {
+    let colors = [
+        &palette.color0,
+        &palette.color1,
+        &palette.color2,
+        &palette.color3,
+    ];
+    let columns = 2 + palette.color0.accents.len() as u32;
+    let rows = colors.len() as u32;
+    let grid_w = columns * (CELL_SIZE + CELL_GAP) - CELL_GAP;
+    let grid_h = rows * (CELL_SIZE + CELL_GAP) - CELL_GAP;
+    // load thumbnail of original wallpaper when available
+    let thumbnail = image::open(&palette.file)
+        .map(|img| img.thumbnail(grid_h * 4, grid_h).into_rgb8())
+        .map_err(|err| log::warn!("skipping wallpaper thumbnail: {err}"))
+        .ok();
+    let thumb_w = thumbnail
+        .as_ref()
+        .map(|t| t.width() + SHEET_MARGIN)
+        .unwrap_or(0);
+    // draw background, thumbnail and color grid
+    let base = if palette.theme == Theme::Light {
+        0xE8
+    } else {
+        0x18
+    };
+    let width = SHEET_MARGIN * 2 + thumb_w + grid_w;
+    let height = SHEET_MARGIN * 2 + grid_h;
+    let mut img = RgbImage::from_pixel(width, height, Rgb([base, base, base]));
+    if let Some(thumbnail) = thumbnail.as_ref() {
+        let y = SHEET_MARGIN + (grid_h - thumbnail.height()) / 2;
+        imageops::overlay(&mut img, thumbnail, SHEET_MARGIN as i64, y as i64);
+    }
+    for (row, color) in colors.into_iter().enumerate() {
+        let y = SHEET_MARGIN + row as u32 * (CELL_SIZE + CELL_GAP);
+        let cells = [&color.primary, &color.text]
+            .into_iter()
+            .chain(color.accents.iter());
+        for (col, cell) in cells.enumerate() {
+            let x = SHEET_MARGIN + thumb_w + col as u32 * (CELL_SIZE + CELL_GAP);
+            fill_cell(&mut img, x, y, cell);
+        }
+    }
+    img
+}
+
+/// Render palette swatch sheet and save it as a PNG
+pub fn save_sheet(palette: &Palette, output: &Path) -> Result<()> {
+    log::info!("writing palette preview to {output:?}");
+    sheet(palette)
+        .save_with_format(output, image::ImageFormat::Png)
+        .context("failed to write preview image")
+}
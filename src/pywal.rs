@@ -0,0 +1,117 @@
+//! Pywal `colors.json` Compatibility
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use serde::{de::Error, ser::SerializeMap, Deserialize, Serialize};
+
+use crate::color::{Color, Gradiant, Palette, Primaries};
+
+/// Palette slots exported as pywal's sixteen terminal colors
+///
+/// Normal colors use mid-range accents of the three non-background primaries
+/// and bright colors use the next lighter accents.
+const COLOR_MAP: [&str; 16] = [
+    "color0.primary",
+    "color1.accents[4]",
+    "color2.accents[4]",
+    "color3.accents[4]",
+    "color1.accents[6]",
+    "color2.accents[6]",
+    "color3.accents[6]",
+    "color0.text",
+    "color0.accents[2]",
+    "color1.accents[5]",
+    "color2.accents[5]",
+    "color3.accents[5]",
+    "color1.accents[7]",
+    "color2.accents[7]",
+    "color3.accents[7]",
+    "color0.text",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Special {
+    pub background: Color,
+    pub foreground: Color,
+    pub cursor: Color,
+}
+
+/// Sixteen terminal colors serialized as `color0`..`color15`
+#[derive(Debug)]
+pub struct TerminalColors(pub [Color; 16]);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PywalColors {
+    pub wallpaper: String,
+    #[serde(default = "default_alpha")]
+    pub alpha: String,
+    pub special: Special,
+    pub colors: TerminalColors,
+}
+
+fn default_alpha() -> String {
+    "100".to_owned()
+}
+
+impl PywalColors {
+    /// Export palette in pywal's color scheme format
+    pub fn from_palette(palette: &Palette) -> Self {
+        let colors =
+            COLOR_MAP.map(|key| palette.get(key).expect("invalid pywal color map").clone());
+        Self {
+            wallpaper: palette.file.clone(),
+            alpha: default_alpha(),
+            special: Special {
+                background: palette.color0.primary.clone(),
+                foreground: palette.color0.text.clone(),
+                cursor: palette.color0.text.clone(),
+            },
+            colors: TerminalColors(colors),
+        }
+    }
+    /// Build a palette from the pywal background and first three colors
+    pub fn to_palette(&self, gradiant: Gradiant) -> Result<Palette> {
+        let mut colors: Vec<Color> = self.colors.0[1..4].to_vec();
+        colors.insert(0, self.special.background.clone());
+        let primaries = Primaries::from_colors(&self.wallpaper, colors);
+        Ok(Palette::from_primaries(&primaries, gradiant)?)
+    }
+    /// Parse pywal colors from json
+    pub fn from_json(s: &str) -> Result<Self> {
+        serde_json::from_str(s).map_err(|err| anyhow!("invalid pywal colors: {err}"))
+    }
+    /// Serialize pywal colors into json
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl Serialize for TerminalColors {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (n, color) in self.0.iter().enumerate() {
+            map.serialize_entry(&format!("color{n}"), color)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for TerminalColors {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut map: HashMap<String, Color> = Deserialize::deserialize(deserializer)?;
+        let colors: Vec<Color> = (0..16)
+            .map(|n| {
+                map.remove(&format!("color{n}"))
+                    .ok_or_else(|| D::Error::custom(format!("missing color{n}")))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self(colors.try_into().expect("sixteen colors")))
+    }
+}
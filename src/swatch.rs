@@ -0,0 +1,410 @@
+//! Designer Swatch Formats (GIMP/Inkscape, Adobe and Krita)
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+
+use anyhow::{anyhow, Context, Result};
+use palette::Srgb;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::color::{Color, Gradiant, Palette, PaletteColor, Primaries, Theme};
+
+/// Palette name written into swatch files (followed by the gradiant)
+const SWATCH_NAME: &str = "wallbash";
+/// Number of swatches in each palette color row (primary, text and accents)
+const SWATCH_COLUMNS: usize = 11;
+/// Adobe Swatch Exchange file signature
+const ASE_MAGIC: &[u8; 4] = b"ASEF";
+/// Adobe Swatch Exchange color entry block type
+const ASE_COLOR_ENTRY: u16 = 0x0001;
+/// Adobe Swatch Exchange group start block type
+const ASE_GROUP_START: u16 = 0xC001;
+/// Adobe Swatch Exchange group end block type
+const ASE_GROUP_END: u16 = 0xC002;
+/// Krita palette mimetype stored within the archive
+const KPL_MIMETYPE: &str = "krita/x-colorset";
+
+/// Named colors read from a swatch file
+#[derive(Debug, Default)]
+pub struct Swatches {
+    /// Palette name stored in the file, if any
+    pub name: Option<String>,
+    pub colors: Vec<(String, Color)>,
+}
+
+/// Palette name of swatch files, recording the gradiant of the accents
+fn swatch_name(palette: &Palette) -> String {
+    format!("{SWATCH_NAME}-{}", palette.gradiant)
+}
+
+/// Recover the gradiant from a palette name written by [`swatch_name`]
+fn swatch_gradiant(name: &str) -> Option<Gradiant> {
+    let gradiant = name.strip_prefix(SWATCH_NAME)?.strip_prefix('-')?;
+    gradiant.parse().ok()
+}
+
+/// List palette colors with swatch names (e.g. `color0-primary`, `color0-accent-3`)
+pub fn named_colors(palette: &Palette) -> Vec<(String, Color)> {
+    let mut colors = vec![];
+    for (n, color) in palette.colors().into_iter().enumerate() {
+        colors.push((format!("color{n}-primary"), color.primary.clone()));
+        colors.push((format!("color{n}-text"), color.text.clone()));
+        for (i, accent) in color.accents.iter().enumerate() {
+            colors.push((format!("color{n}-accent-{}", i + 1), accent.clone()));
+        }
+    }
+    colors
+}
+
+/// Rebuild palette from named swatches
+///
+/// Swatches following the wallbash naming scheme are restored exactly,
+/// otherwise the first four swatches are used as primary colors.
+pub fn from_named_colors(file: &str, swatches: Swatches) -> Result<Palette> {
+    let Swatches { name, colors } = swatches;
+    let mut named: HashMap<String, Color> = colors.iter().cloned().collect();
+    let mut take = |name: String| named.remove(&name);
+    let restored: Option<Vec<PaletteColor>> = (0..4)
+        .map(|n| {
+            let accents: Option<Vec<Color>> = (1..=9)
+                .map(|i| take(format!("color{n}-accent-{i}")))
+                .collect();
+            Some(PaletteColor {
+                primary: take(format!("color{n}-primary"))?,
+                text: take(format!("color{n}-text"))?,
+                accents: accents?.try_into().ok()?,
+            })
+        })
+        .collect();
+    if let Some(mut restored) = restored {
+        let theme = match restored[0].primary > restored[3].primary {
+            true => Theme::Light,
+            false => Theme::Dark,
+        };
+        // files without a recorded gradiant predate it and used standard
+        let gradiant = name.as_deref().and_then(swatch_gradiant);
+        return Ok(Palette {
+            file: file.to_owned(),
+            theme,
+            gradiant: gradiant.unwrap_or(Gradiant::Standard),
+            color3: restored.pop().expect("four colors"),
+            color2: restored.pop().expect("four colors"),
+            color1: restored.pop().expect("four colors"),
+            color0: restored.pop().expect("four colors"),
+            synthesized: vec![],
+            material: None,
+            dark: None,
+            light: None,
+        });
+    }
+    if colors.len() < 4 {
+        return Err(anyhow!("swatch file needs at least four colors"));
+    }
+    log::warn!("swatches not named by wallbash. using first four as primaries");
+    let colors = colors.into_iter().take(4).map(|(_, c)| c).collect();
+    let primaries = Primaries::from_colors(file, colors);
+    Ok(Palette::from_primaries(&primaries, Gradiant::Auto)?)
+}
+
+/// Export palette as a GIMP/Inkscape `.gpl` palette
+pub fn to_gpl(palette: &Palette) -> String {
+    let name = swatch_name(palette);
+    let mut gpl = format!("GIMP Palette\nName: {name}\nColumns: {SWATCH_COLUMNS}\n#\n");
+    for (name, color) in named_colors(palette) {
+        let (r, g, b) = color.rgb();
+        gpl.push_str(&format!("{r:>3} {g:>3} {b:>3}\t{name}\n"));
+    }
+    gpl
+}
+
+/// Import named colors from a GIMP/Inkscape `.gpl` palette
+pub fn from_gpl(content: &str) -> Result<Swatches> {
+    let mut lines = content.lines();
+    if lines.next().map(|l| l.trim()) != Some("GIMP Palette") {
+        return Err(anyhow!("missing gimp palette header"));
+    }
+    let mut swatches = Swatches::default();
+    for line in lines {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("Name:") {
+            swatches.name = Some(name.trim().to_owned());
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') || line.starts_with("Columns:") {
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        let mut channel = || -> Result<u8> {
+            let value = parts.next().context("missing color channel")?;
+            value
+                .parse()
+                .context(format!("invalid color channel {value:?}"))
+        };
+        let (r, g, b) = (channel()?, channel()?, channel()?);
+        let name = parts.collect::<Vec<_>>().join(" ");
+        swatches.colors.push((name, Color::from_rgb(r, g, b)));
+    }
+    Ok(swatches)
+}
+
+/// Export palette as an Adobe Swatch Exchange `.ase` file
+pub fn to_ase(palette: &Palette) -> Vec<u8> {
+    let colors = named_colors(palette);
+    let mut ase = ASE_MAGIC.to_vec();
+    ase.extend(1u16.to_be_bytes());
+    ase.extend(0u16.to_be_bytes());
+    // colors are wrapped within a group carrying the palette name
+    ase.extend((colors.len() as u32 + 2).to_be_bytes());
+    let group = ase_name(&swatch_name(palette));
+    ase.extend(ASE_GROUP_START.to_be_bytes());
+    ase.extend((group.len() as u32).to_be_bytes());
+    ase.extend(group);
+    for (name, color) in colors {
+        let mut block = ase_name(&name);
+        block.extend(b"RGB ");
+        for channel in [color.0.red, color.0.green, color.0.blue] {
+            block.extend(channel.to_be_bytes());
+        }
+        // global color type
+        block.extend(2u16.to_be_bytes());
+        ase.extend(ASE_COLOR_ENTRY.to_be_bytes());
+        ase.extend((block.len() as u32).to_be_bytes());
+        ase.extend(block);
+    }
+    ase.extend(ASE_GROUP_END.to_be_bytes());
+    ase.extend(0u32.to_be_bytes());
+    ase
+}
+
+/// Encode a length prefixed, nul terminated utf-16 ase name
+fn ase_name(name: &str) -> Vec<u8> {
+    let name: Vec<u16> = name.encode_utf16().chain([0]).collect();
+    let mut encoded = (name.len() as u16).to_be_bytes().to_vec();
+    name.iter().for_each(|c| encoded.extend(c.to_be_bytes()));
+    encoded
+}
+
+/// Decode an ase name from the start of a block, returning the name length in bytes
+fn read_ase_name(block: &[u8]) -> Result<(String, usize)> {
+    let chars = block.get(0..2).context("invalid ase color name")?;
+    let chars = u16::from_be_bytes([chars[0], chars[1]]) as usize;
+    let end = 2 + chars * 2;
+    let name: Vec<u16> = block
+        .get(2..end)
+        .context("invalid ase color name")?
+        .chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+    Ok((String::from_utf16_lossy(&name), end))
+}
+
+/// Import named colors from an Adobe Swatch Exchange `.ase` file
+pub fn from_ase(data: &[u8]) -> Result<Swatches> {
+    let mut cursor = Cursor::new(data);
+    let mut read = |n: usize| -> Result<Vec<u8>> {
+        // reject declared lengths past the end of file before allocating
+        let remaining = data.len() as u64 - cursor.position();
+        if n as u64 > remaining {
+            return Err(anyhow!("unexpected end of ase file"));
+        }
+        let mut buf = vec![0; n];
+        cursor
+            .read_exact(&mut buf)
+            .context("unexpected end of ase file")?;
+        Ok(buf)
+    };
+    if read(4)? != ASE_MAGIC {
+        return Err(anyhow!("missing ase file signature"));
+    }
+    read(4)?;
+    let blocks = u32::from_be_bytes(read(4)?.try_into().expect("four bytes"));
+    let mut swatches = Swatches::default();
+    for _ in 0..blocks {
+        let kind = u16::from_be_bytes(read(2)?.try_into().expect("two bytes"));
+        let len = u32::from_be_bytes(read(4)?.try_into().expect("four bytes"));
+        let block = read(len as usize)?;
+        if kind == ASE_GROUP_START && swatches.name.is_none() {
+            swatches.name = Some(read_ase_name(&block)?.0);
+        }
+        if kind != ASE_COLOR_ENTRY {
+            continue;
+        }
+        let (name, end) = read_ase_name(&block)?;
+        let model = block.get(end..end + 4).context("invalid ase color model")?;
+        let values: Vec<f32> = block[end + 4..]
+            .chunks_exact(4)
+            .map(|c| f32::from_be_bytes(c.try_into().expect("four bytes")))
+            .collect();
+        let color = match (model, values.as_slice()) {
+            (b"RGB ", [r, g, b, ..]) => Color(Srgb::new(*r, *g, *b)),
+            (b"Gray", [v, ..]) => Color(Srgb::new(*v, *v, *v)),
+            _ => {
+                log::warn!("skipping unsupported ase color {name:?}");
+                continue;
+            }
+        };
+        swatches.colors.push((name, color));
+    }
+    Ok(swatches)
+}
+
+/// Export palette as a Krita `.kpl` palette archive
+pub fn to_kpl(palette: &Palette) -> Result<Vec<u8>> {
+    let colors = named_colors(palette);
+    let rows = colors.len().div_ceil(SWATCH_COLUMNS);
+    let name = swatch_name(palette);
+    let mut xml = format!(
+        "<Colorset version=\"1.0\" name=\"{name}\" comment=\"\" \
+         columns=\"{SWATCH_COLUMNS}\" rows=\"{rows}\">\n"
+    );
+    for (n, (name, color)) in colors.iter().enumerate() {
+        let (row, column) = (n / SWATCH_COLUMNS, n % SWATCH_COLUMNS);
+        let (r, g, b) = (color.0.red, color.0.green, color.0.blue);
+        xml.push_str(&format!(
+            " <ColorSetEntry name=\"{name}\" id=\"{name}\" spot=\"false\" bitdepth=\"U8\">\n  \
+             <RGB space=\"sRGB-elle-V2-srgbtrc.icc\" r=\"{r}\" g=\"{g}\" b=\"{b}\"/>\n  \
+             <Position row=\"{row}\" column=\"{column}\"/>\n </ColorSetEntry>\n"
+        ));
+    }
+    xml.push_str("</Colorset>\n");
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(KPL_MIMETYPE.as_bytes())?;
+    zip.start_file("colorset.xml", SimpleFileOptions::default())?;
+    zip.write_all(xml.as_bytes())?;
+    zip.start_file("profiles.xml", SimpleFileOptions::default())?;
+    zip.write_all(b"<Profiles/>\n")?;
+    Ok(zip
+        .finish()
+        .context("failed to write kpl archive")?
+        .into_inner())
+}
+
+/// Extract an attribute value from an xml tag
+fn xml_attr<'a>(tag: &'a str, attr: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {attr}=\""))? + attr.len() + 3;
+    let end = tag[start..].find('"')? + start;
+    Some(&tag[start..end])
+}
+
+/// Import named colors from a Krita `.kpl` palette archive
+pub fn from_kpl(data: &[u8]) -> Result<Swatches> {
+    let mut zip = ZipArchive::new(Cursor::new(data)).context("invalid kpl archive")?;
+    let mut xml = String::new();
+    zip.by_name("colorset.xml")
+        .context("kpl archive missing colorset.xml")?
+        .read_to_string(&mut xml)
+        .context("failed to read colorset.xml")?;
+    let mut entries = xml.split("<ColorSetEntry");
+    let header = entries.next().unwrap_or_default();
+    let mut swatches = Swatches {
+        name: header
+            .split("<Colorset")
+            .nth(1)
+            .and_then(|tag| xml_attr(tag, "name"))
+            .map(str::to_owned),
+        colors: vec![],
+    };
+    for entry in entries {
+        let name = xml_attr(entry, "name").unwrap_or_default().to_owned();
+        let Some(rgb) = entry.split("<RGB").nth(1) else {
+            log::warn!("skipping non-rgb kpl color {name:?}");
+            continue;
+        };
+        let channel = |c: &str| -> Result<f32> {
+            let value = xml_attr(rgb, c).context(format!("kpl color {name:?} missing {c}"))?;
+            value
+                .parse()
+                .context(format!("invalid kpl channel {value:?}"))
+        };
+        let color = Color(Srgb::new(channel("r")?, channel("g")?, channel("b")?));
+        swatches.colors.push((name, color));
+    }
+    Ok(swatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::tests::{palette, primaries};
+
+    fn hexes(colors: &[(String, Color)]) -> Vec<(String, String)> {
+        colors.iter().map(|(n, c)| (n.clone(), c.hex())).collect()
+    }
+
+    #[test]
+    fn swatches_round_trip() {
+        let palette = palette();
+        let expected = hexes(&named_colors(&palette));
+        assert_eq!(
+            hexes(&from_gpl(&to_gpl(&palette)).unwrap().colors),
+            expected
+        );
+        assert_eq!(
+            hexes(&from_ase(&to_ase(&palette)).unwrap().colors),
+            expected
+        );
+        let kpl = to_kpl(&palette).unwrap();
+        assert_eq!(hexes(&from_kpl(&kpl).unwrap().colors), expected);
+        let restored = from_named_colors("test", from_ase(&to_ase(&palette)).unwrap()).unwrap();
+        assert_eq!(hexes(&named_colors(&restored)), expected);
+    }
+
+    #[test]
+    fn swatches_keep_the_gradiant() {
+        let palette = Palette::from_primaries(&primaries(), Gradiant::Vibrant).unwrap();
+        let mut light = palette.clone();
+        light.set_theme(Theme::Light).unwrap();
+        let swatches = [
+            from_gpl(&to_gpl(&palette)).unwrap(),
+            from_ase(&to_ase(&palette)).unwrap(),
+            from_kpl(&to_kpl(&palette).unwrap()).unwrap(),
+        ];
+        for swatches in swatches {
+            let mut restored = from_named_colors("test", swatches).unwrap();
+            assert_eq!(restored.gradiant, Gradiant::Vibrant);
+            restored.set_theme(Theme::Light).unwrap();
+            assert_eq!(
+                hexes(&named_colors(&restored)),
+                hexes(&named_colors(&light))
+            );
+        }
+    }
+
+    #[test]
+    fn gpl_keeps_names_containing_colons() {
+        let gpl = "GIMP Palette\nName: test\nColumns: 4\n#\n255   0   0\tAccent: red\n";
+        let swatches = from_gpl(gpl).unwrap();
+        assert_eq!(swatches.name.as_deref(), Some("test"));
+        assert_eq!(
+            hexes(&swatches.colors),
+            [("Accent: red".to_owned(), "#FF0000".to_owned())]
+        );
+    }
+
+    #[test]
+    fn ase_rejects_truncated_blocks() {
+        let ase = to_ase(&palette());
+        assert!(from_ase(&ase[..ase.len() - 3]).is_err());
+        // color entry with a single byte block
+        let mut short = ASE_MAGIC.to_vec();
+        short.extend([0, 1, 0, 0, 0, 0, 0, 1]);
+        short.extend(ASE_COLOR_ENTRY.to_be_bytes());
+        short.extend(1u32.to_be_bytes());
+        short.push(0);
+        assert!(from_ase(&short).is_err());
+    }
+
+    #[test]
+    fn ase_rejects_oversized_blocks() {
+        let mut ase = ASE_MAGIC.to_vec();
+        ase.extend([0, 1, 0, 0, 0, 0, 0, 1]);
+        ase.extend(ASE_COLOR_ENTRY.to_be_bytes());
+        ase.extend(u32::MAX.to_be_bytes());
+        ase.extend([0; 16]);
+        let err = from_ase(&ase).unwrap_err().to_string();
+        assert!(err.contains("unexpected end"), "{err}");
+    }
+}
@@ -1,11 +1,12 @@
 //! Template Autofill
 
-use anyhow::{Context, Result};
-use minijinja::{context, Environment};
+use minijinja::{context, Environment, Value};
+use serde::Serialize;
 
 use crate::color::{Color, Palette};
+use crate::error::{Error, Result};
 
-fn parse_color(s: String) -> Result<Color, minijinja::Error> {
+fn parse_color(s: String) -> std::result::Result<Color, minijinja::Error> {
     Color::from_hex(&s).map_err(|_| {
         minijinja::Error::new(
             minijinja::ErrorKind::CannotDeserialize,
@@ -14,31 +15,72 @@ fn parse_color(s: String) -> Result<Color, minijinja::Error> {
     })
 }
 
-fn rgb(s: String) -> Result<String, minijinja::Error> {
+fn rgb(s: String) -> std::result::Result<String, minijinja::Error> {
     let c = parse_color(s)?;
     let (r, g, b) = c.rgb();
     Ok(format!("rgb({r},{g},{b})"))
 }
 
+fn hex(s: String) -> std::result::Result<String, minijinja::Error> {
+    let c = parse_color(s)?;
+    let (r, g, b) = c.rgb();
+    Ok(format!("{r:02X}{g:02X}{b:02X}"))
+}
+
+/// Template renderer filling palette colors into jinja templates
+///
+/// Templates can access `file`, `theme`, `gradiant` and `color0`..`color3`,
+/// the `dark` and `light` variants of those colors and the `material`
+/// schemes along with any user-defined variables, and the `rgb` and `hex`
+/// filters.
 pub struct Engine<'a> {
     env: Environment<'a>,
+    vars: Value,
+}
+
+impl Default for Engine<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'a> Engine<'a> {
+    /// Create engine with the builtin color filters registered
     pub fn new() -> Self {
         let mut env = Environment::new();
         env.add_filter("rgb", rgb);
-        Self { env }
+        env.add_filter("hex", hex);
+        Self {
+            env,
+            vars: context!(),
+        }
+    }
+    /// Add user-defined variables to the template context
+    ///
+    /// Palette values take precedence over variables with the same name.
+    pub fn with_vars<S: Serialize>(mut self, vars: &S) -> Self {
+        self.vars = Value::from_serialize(vars);
+        self
+    }
+    /// Compile template to check for syntax errors without rendering
+    pub fn check(&mut self, template: &'a str) -> Result<()> {
+        self.add_template("main", template)
     }
+    /// Render template with the palette colors
     pub fn render(&mut self, template: &'a str, palette: &Palette) -> Result<String> {
+        self.add_template("main", template)?;
+        self.render_template("main", palette)
+    }
+    /// Compile a named template once for repeated [`Engine::render_template`] calls
+    pub fn add_template(&mut self, name: &'a str, template: &'a str) -> Result<()> {
         self.env
-            .add_template("main", &template)
-            .context("failed to add template")?;
-        let tmpl = self
-            .env
-            .get_template("main")
-            .context("failed to load template")?;
-        Ok(tmpl.render(context!(
+            .add_template(name, template)
+            .map_err(Error::Template)
+    }
+    /// Render a template added with [`Engine::add_template`] with the palette colors
+    pub fn render_template(&self, name: &str, palette: &Palette) -> Result<String> {
+        let tmpl = self.env.get_template(name).map_err(Error::Template)?;
+        tmpl.render(context!(
             file => palette.file,
             theme => palette.theme,
             gradiant => palette.gradiant,
@@ -46,6 +88,12 @@ impl<'a> Engine<'a> {
             color1 => palette.color1,
             color2 => palette.color2,
             color3 => palette.color3,
-        ))?)
+            synthesized => palette.synthesized,
+            material => palette.material,
+            dark => palette.dark,
+            light => palette.light,
+            ..self.vars.clone()
+        ))
+        .map_err(Error::Template)
     }
 }